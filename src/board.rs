@@ -29,13 +29,23 @@ impl Move {
         Move { from: 0, to: 0, promotion: None, captured: None, is_ep: false, is_castle: false }
     }
 
-    pub fn to_uci(&self) -> String {
+    /// No capture (en passant included) and no promotion — the moves that
+    /// killers, history and LMR deal with
+    pub fn is_quiet(&self) -> bool {
+        self.captured.is_none() && self.promotion.is_none() && !self.is_ep
+    }
+
+    /// Same piece motion, ignoring `captured` — a move remembered from
+    /// another position (TT, killers) may carry a stale capture.
+    pub fn same_motion(&self, other: &Move) -> bool {
+        self.from == other.from && self.to == other.to
+            && self.promotion == other.promotion
+            && self.is_castle == other.is_castle
+            && self.is_ep == other.is_ep
+    }
+
+    pub fn to_uci(self) -> String {
         if self.from == 0 && self.to == 0 { return "0000".to_string(); }
-        let files = "abcdefgh";
-        let from_f = files.chars().nth((self.from % 8) as usize).unwrap();
-        let from_r = (self.from / 8) + 1;
-        let to_f   = files.chars().nth((self.to % 8) as usize).unwrap();
-        let to_r   = (self.to / 8) + 1;
         let promo  = match self.promotion {
             Some(Piece::Queen)  => "q",
             Some(Piece::Rook)   => "r",
@@ -43,20 +53,54 @@ impl Move {
             Some(Piece::Knight) => "n",
             _ => "",
         };
-        format!("{}{}{}{}{}", from_f, from_r, to_f, to_r, promo)
+        format!("{}{}{}", sq_to_str(self.from), sq_to_str(self.to), promo)
     }
 }
 
+// Castling-rights bits in `Position::castling`
+pub const WK: u8 = 0b0001;
+pub const WQ: u8 = 0b0010;
+pub const BK: u8 = 0b0100;
+pub const BQ: u8 = 0b1000;
+
+// FEN move counters are capped here, far past any real game, so the ply
+// arithmetic on them cannot overflow
+const MAX_FEN_MOVES: u32 = 1_000_000;
+
 #[derive(Clone)]
 pub struct Board {
+    pos: Position,                 // the current position; `Board` derefs to it
+    pub halfmove: u32,
+    start_ply: u32,                // game ply of the FEN position (from its move number)
+    history: Vec<HistoryEntry>,
+    pub position_hashes: Vec<u64>, // for repetition detection
+    last_reset: usize,             // position_hashes index of the last irreversible move
+}
+
+/// A position without the game around it: placement, side to move, rights
+/// and hash, plus the material counts kept alongside. No history, so it is
+/// `Copy`: movegen plays a move on a copy to test its legality, and eval
+/// and movegen take a `&Position`, which a `&Board` derefs to.
+/// `Board::from_position` turns it back into a board.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Position {
     pub squares: [Option<ColoredPiece>; 64],
     pub side: Color,
     pub castling: u8,
     pub ep_square: Option<u8>,
-    pub halfmove: u32,
     pub hash: u64,
-    history: Vec<HistoryEntry>,
-    pub position_hashes: Vec<u64>, // for repetition detection
+    piece_counts: [[u8; 6]; 2],    // [color][piece], kept up to date by make/unmake
+    pawn_files:   [[u8; 8]; 2],    // [color][file] pawn counts, likewise
+    phase_units: i32,              // non-pawn material in phase units, likewise
+}
+
+impl std::ops::Deref for Board {
+    type Target = Position;
+    fn deref(&self) -> &Position { &self.pos }
+}
+
+impl std::ops::DerefMut for Board {
+    fn deref_mut(&mut self) -> &mut Position { &mut self.pos }
 }
 
 #[derive(Clone)]
@@ -66,6 +110,7 @@ struct HistoryEntry {
     ep_square: Option<u8>,
     halfmove: u32,
     hash: u64,
+    last_reset: usize,
 }
 
 impl Board {
@@ -74,22 +119,15 @@ impl Board {
     }
 
     pub fn from_fen(fen: &str) -> Self {
-        let mut board = Board {
-            squares: [None; 64],
-            side: Color::White,
-            castling: 0b1111,
-            ep_square: None,
-            halfmove: 0,
-            hash: 0,
-            history: Vec::new(),
-            position_hashes: Vec::new(),
-        };
+        let mut pos = Position::empty();
 
-        let parts: Vec<&str> = fen.split(' ').collect();
+        // Trailing fields (castling, ep, clocks) may be missing; GUIs do send
+        // such FENs, so every field past the placement has a default.
+        let parts: Vec<&str> = fen.split_whitespace().collect();
 
         let mut rank = 7i32;
         let mut file = 0i32;
-        for ch in parts[0].chars() {
+        for ch in parts.first().copied().unwrap_or("").chars() {
             match ch {
                 '/' => { rank -= 1; file = 0; }
                 '1'..='8' => { file += ch as i32 - '0' as i32; }
@@ -100,9 +138,9 @@ impl Board {
                         'r' => Piece::Rook, 'q' => Piece::Queen,  'k' => Piece::King,
                         _ => { file += 1; continue; }
                     };
-                    let sq = (rank * 8 + file) as usize;
-                    if sq < 64 {
-                        board.squares[sq] = Some(ColoredPiece { piece, color });
+                    let sq = make_sq(file, rank) as usize;
+                    if sq < 64 && pos.squares[sq].is_none() {
+                        pos.squares[sq] = Some(ColoredPiece { piece, color });
                     }
                     file += 1;
                 }
@@ -110,117 +148,154 @@ impl Board {
         }
 
         if parts.len() > 1 {
-            board.side = if parts[1] == "b" { Color::Black } else { Color::White };
+            pos.side = if parts[1] == "b" { Color::Black } else { Color::White };
         }
 
-        board.castling = 0;
         if parts.len() > 2 {
             let c = parts[2];
-            if c.contains('K') { board.castling |= 0b0001; }
-            if c.contains('Q') { board.castling |= 0b0010; }
-            if c.contains('k') { board.castling |= 0b0100; }
-            if c.contains('q') { board.castling |= 0b1000; }
+            if c.contains('K') { pos.castling |= WK; }
+            if c.contains('Q') { pos.castling |= WQ; }
+            if c.contains('k') { pos.castling |= BK; }
+            if c.contains('q') { pos.castling |= BQ; }
+            // A right whose king or rook is off its home square is bogus;
+            // keeping it would let movegen castle illegally
+            pos.castling &= pos.home_castling();
+        } else {
+            pos.castling = pos.home_castling();
         }
 
         if parts.len() > 3 && parts[3] != "-" {
-            board.ep_square = sq_from_str(parts[3]);
+            pos.ep_square = sq_from_str(parts[3]);
         }
 
-        board
+        let halfmove = parts.get(4).and_then(|s| s.parse().ok()).unwrap_or(0).min(MAX_FEN_MOVES);
+
+        let fullmove: u32 = parts.get(5).and_then(|s| s.parse().ok()).unwrap_or(1).clamp(1, MAX_FEN_MOVES);
+        let start_ply = (fullmove - 1) * 2 + (pos.side == Color::Black) as u32;
+
+        pos.refresh();
+        Board::new(pos, halfmove, start_ply)
     }
 
-    pub fn piece_at(&self, sq: u8) -> Option<ColoredPiece> {
-        self.squares[sq as usize]
+    // A board at `pos` with no moves made on it yet
+    fn new(pos: Position, halfmove: u32, start_ply: u32) -> Self {
+        Board { pos, halfmove, start_ply, history: Vec::new(), position_hashes: Vec::new(), last_reset: 0 }
+    }
+
+    pub fn position(&self) -> Position {
+        self.pos
+    }
+
+    /// A board for `pos` with no game history (halfmove clock 0). The
+    /// counts and hash are recomputed from the squares rather than trusted.
+    pub fn from_position(mut pos: Position) -> Self {
+        pos.refresh();
+        Board::new(pos, 0, (pos.side == Color::Black) as u32)
+    }
+
+    /// The same position with the board flipped top to bottom and the
+    /// colors swapped — a correct evaluation scores it identically for the
+    /// side to move. Move history is not carried over.
+    pub fn mirror(&self) -> Board {
+        let mut squares = [None; 64];
+        for sq in 0..64 {
+            squares[sq ^ 56] = self.squares[sq]
+                .map(|cp| ColoredPiece { piece: cp.piece, color: opposite(cp.color) });
+        }
+        let c = self.castling;
+        let mut pos = Position {
+            squares,
+            side: opposite(self.side),
+            castling: ((c & (WK | WQ)) << 2) | ((c & (BK | BQ)) >> 2),
+            ep_square: self.ep_square.map(|sq| sq ^ 56),
+            hash: 0,
+            piece_counts: [self.piece_counts[1], self.piece_counts[0]],
+            pawn_files:   [self.pawn_files[1], self.pawn_files[0]],
+            phase_units: self.phase_units,
+        };
+        pos.hash = crate::zobrist::keys().hash(&pos);
+        let start_ply = (self.fullmove() - 1) * 2 + (self.side == Color::White) as u32;
+        Board::new(pos, self.halfmove, start_ply)
+    }
+
+    /// Plies since the start of the game: the FEN's move number and side
+    /// to move, plus every move made on this board since
+    pub fn game_ply(&self) -> u32 {
+        self.start_ply + self.history.len() as u32
+    }
+
+    /// The FEN fullmove number, counting on from the FEN's own
+    pub fn fullmove(&self) -> u32 {
+        self.game_ply() / 2 + 1
     }
 
     pub fn make_move(&mut self, mv: Move) {
+        // A move with no piece on `from`, or off-board squares (a bad API
+        // call, a corrupt TT move), is not played: only the turn passes,
+        // and it is recorded as the null move so unmake_move undoes just that
+        let moving = self.pos.mover(mv);
+
         // Store hash for repetition detection
         self.position_hashes.push(self.hash);
 
         self.history.push(HistoryEntry {
-            mv,
+            mv: if moving.is_some() { mv } else { Move::null() },
             castling: self.castling,
             ep_square: self.ep_square,
             halfmove: self.halfmove,
             hash: self.hash,
+            last_reset: self.last_reset,
         });
 
-        let moving = match self.squares[mv.from as usize] {
-            Some(p) => p,
-            None => { self.side = opposite(self.side); return; }
-        };
-
-        if mv.is_castle {
-            self.squares[mv.to as usize] = Some(moving);
-            self.squares[mv.from as usize] = None;
-            let (rook_from, rook_to) = if mv.to > mv.from {
-                (mv.from + 3, mv.from + 1)
-            } else {
-                (mv.from - 4, mv.from - 1)
-            };
-            if rook_from < 64 && rook_to < 64 {
-                let rook = self.squares[rook_from as usize];
-                self.squares[rook_to as usize] = rook;
-                self.squares[rook_from as usize] = None;
-            }
-        } else {
-            if mv.is_ep {
-                let ep_pawn_sq = if self.side == Color::White {
-                    mv.to.wrapping_sub(8)
-                } else {
-                    mv.to + 8
-                };
-                if ep_pawn_sq < 64 {
-                    self.squares[ep_pawn_sq as usize] = None;
-                }
-            }
-
-            self.squares[mv.to as usize] = if let Some(promo) = mv.promotion {
-                Some(ColoredPiece { piece: promo, color: moving.color })
-            } else {
-                Some(moving)
-            };
-            self.squares[mv.from as usize] = None;
-        }
+        self.pos.make_move(mv);
+        let Some(moving) = moving else { return };
 
-        if matches!(moving.piece, Piece::King) {
-            match moving.color {
-                Color::White => self.castling &= !0b0011,
-                Color::Black => self.castling &= !0b1100,
-            }
-        }
-        match mv.from {
-            0  => self.castling &= !0b0010,
-            7  => self.castling &= !0b0001,
-            56 => self.castling &= !0b1000,
-            63 => self.castling &= !0b0100,
-            _  => {}
-        }
-        match mv.to {
-            0  => self.castling &= !0b0010,
-            7  => self.castling &= !0b0001,
-            56 => self.castling &= !0b1000,
-            63 => self.castling &= !0b0100,
-            _  => {}
-        }
-
-        self.ep_square = if matches!(moving.piece, Piece::Pawn) {
-            let diff = (mv.to as i32 - mv.from as i32).abs();
-            if diff == 16 {
-                Some((mv.from + mv.to) / 2)
-            } else { None }
-        } else { None };
-
-        // Reset halfmove on pawn move or capture
+        // Reset halfmove on pawn move (promotions included) or capture;
+        // castling is reversible for the 50-move rule, so the clock runs on
         if matches!(moving.piece, Piece::Pawn) || mv.captured.is_some() || mv.is_ep {
             self.halfmove = 0;
+            // Nothing before this move can ever be repeated
+            self.last_reset = self.position_hashes.len();
         } else {
             self.halfmove += 1;
         }
+    }
 
+    /// Pass the turn (for null-move pruning). Only the side, ep square and
+    /// hash change; undo with `unmake_null_move`, not `unmake_move`.
+    pub fn make_null_move(&mut self) {
+        self.position_hashes.push(self.hash);
+        self.history.push(HistoryEntry {
+            mv: Move::null(),
+            castling: self.castling,
+            ep_square: self.ep_square,
+            halfmove: self.halfmove,
+            hash: self.hash,
+            last_reset: self.last_reset,
+        });
+        let keys = crate::zobrist::keys();
+        self.hash ^= keys.side() ^ keys.ep(self.ep_square);
+        self.ep_square = None;
+        self.halfmove += 1;
         self.side = opposite(self.side);
     }
 
+    pub fn unmake_null_move(&mut self) {
+        let Some(entry) = self.history.pop() else { return };
+        self.position_hashes.pop();
+        self.castling = entry.castling;
+        self.ep_square = entry.ep_square;
+        self.halfmove = entry.halfmove;
+        self.hash = entry.hash;
+        self.last_reset = entry.last_reset;
+        self.side = opposite(self.side);
+    }
+
+    /// The most recent move made (a null move shows up as `Move::null()`)
+    pub fn last_move(&self) -> Option<Move> {
+        self.history.last().map(|e| e.mv)
+    }
+
     pub fn unmake_move(&mut self) {
         let entry = match self.history.pop() {
             Some(e) => e,
@@ -233,7 +308,9 @@ impl Board {
         self.ep_square = entry.ep_square;
         self.halfmove = entry.halfmove;
         self.hash = entry.hash;
+        self.last_reset = entry.last_reset;
         self.side = opposite(self.side);
+        if mv.from == mv.to { return; } // not played (see make_move)
 
         let moved = self.squares[mv.to as usize];
 
@@ -243,7 +320,7 @@ impl Board {
             let (rook_from, rook_to) = if mv.to > mv.from {
                 (mv.from + 3, mv.from + 1)
             } else {
-                (mv.from - 4, mv.from - 1)
+                (mv.from.wrapping_sub(4), mv.from.wrapping_sub(1))
             };
             if rook_from < 64 && rook_to < 64 {
                 let rook = self.squares[rook_to as usize];
@@ -251,6 +328,11 @@ impl Board {
                 self.squares[rook_to as usize] = None;
             }
         } else {
+            // self.side is the mover again: the pawn is theirs, and any
+            // captured piece (a promotion capture included) the opponent's
+            let pawn = mv.promotion.is_some() || moved.is_some_and(|p| p.piece == Piece::Pawn);
+            let side = self.side;
+            self.pos.update_counts(side, mv, pawn, true);
             let original_piece = if mv.promotion.is_some() {
                 Some(ColoredPiece { piece: Piece::Pawn, color: self.side })
             } else {
@@ -282,22 +364,451 @@ impl Board {
 
     /// Make a move from UCI string — returns false if move is illegal
     pub fn make_uci_move(&mut self, uci: &str) -> bool {
+        if let Some(mv) = self.parse_uci(uci) {
+            self.make_move(mv);
+            return true;
+        }
+        eprintln!("info string WARNING: illegal UCI move attempted: {}", uci);
+        false
+    }
+
+    /// Resolve a UCI string to a legal move in this position. Castling is
+    /// accepted both as the king's two-square step (e1g1) and in the
+    /// king-takes-rook form some GUIs send (e1h1).
+    pub fn parse_uci(&self, uci: &str) -> Option<Move> {
         let moves = crate::movegen::generate_moves(self);
-        for mv in moves {
-            if mv.to_uci() == uci {
-                self.make_move(mv);
-                return true;
+        if let Some(&mv) = moves.iter().find(|mv| mv.to_uci() == uci) {
+            return Some(mv);
+        }
+        moves.into_iter().find(|mv| mv.is_castle && {
+            let rook_sq = if mv.to > mv.from { mv.from + 3 } else { mv.from - 4 };
+            Move { to: rook_sq, ..*mv }.to_uci() == uci
+        })
+    }
+
+    /// Make a move from SAN (e.g. "Nf3", "exd5", "O-O", "e8=Q+") — returns
+    /// false if it is illegal or ambiguous
+    pub fn make_san_move(&mut self, san: &str) -> bool {
+        if let Some(mv) = self.parse_san(san) {
+            self.make_move(mv);
+            return true;
+        }
+        eprintln!("info string WARNING: illegal SAN move attempted: {}", san);
+        false
+    }
+
+    /// Make a move given in either UCI or SAN — UCI is tried first
+    pub fn make_move_str(&mut self, s: &str) -> bool {
+        match self.parse_uci(s).or_else(|| self.parse_san(s)) {
+            Some(mv) => { self.make_move(mv); true }
+            None => {
+                eprintln!("info string WARNING: illegal move attempted: {}", s);
+                false
             }
         }
-        eprintln!("info string WARNING: illegal UCI move attempted: {}", uci);
+    }
+
+    /// SAN for a legal move in this position, e.g. "Nbd7", "gxh8=Q+", "O-O"
+    pub fn to_san(&self, mv: Move) -> String {
+        let Some(moving) = self.squares[mv.from as usize] else { return mv.to_uci() };
+        let file_ch = |sq: u8| (b'a' + file_of(sq) as u8) as char;
+        let rank_ch = |sq: u8| (b'1' + rank_of(sq) as u8) as char;
+        let mut san = String::new();
+
+        if mv.is_castle {
+            san.push_str(if mv.to > mv.from { "O-O" } else { "O-O-O" });
+        } else {
+            let is_capture = mv.captured.is_some() || mv.is_ep;
+            if moving.piece == Piece::Pawn {
+                if is_capture { san.push(file_ch(mv.from)); }
+            } else {
+                san.push(piece_char(moving.piece));
+                // Disambiguate against same-type pieces that can also reach `to`
+                let rivals: Vec<Move> = crate::movegen::generate_moves(self).into_iter()
+                    .filter(|m| m.to == mv.to && m.from != mv.from
+                        && self.squares[m.from as usize].is_some_and(|cp| cp.piece == moving.piece))
+                    .collect();
+                if !rivals.is_empty() {
+                    let same_file = rivals.iter().any(|m| file_of(m.from) == file_of(mv.from));
+                    let same_rank = rivals.iter().any(|m| rank_of(m.from) == rank_of(mv.from));
+                    if !same_file {
+                        san.push(file_ch(mv.from));
+                    } else if !same_rank {
+                        san.push(rank_ch(mv.from));
+                    } else {
+                        san.push(file_ch(mv.from));
+                        san.push(rank_ch(mv.from));
+                    }
+                }
+            }
+            if is_capture { san.push('x'); }
+            san.push_str(&sq_to_str(mv.to));
+            if let Some(promo) = mv.promotion {
+                san.push('=');
+                san.push(piece_char(promo));
+            }
+        }
+
+        let mut after = self.pos;
+        after.make_move(mv);
+        if after.in_check() {
+            san.push(if crate::movegen::has_legal_move(&after) { '+' } else { '#' });
+        }
+        san
+    }
+
+    /// Resolve a SAN string to the unique legal move it describes
+    pub fn parse_san(&self, san: &str) -> Option<Move> {
+        let san = san.trim_end_matches(['+', '#', '!', '?']);
+        let moves = crate::movegen::generate_moves(self);
+
+        if san == "O-O" || san == "0-0" {
+            return moves.into_iter().find(|m| m.is_castle && m.to > m.from);
+        }
+        if san == "O-O-O" || san == "0-0-0" {
+            return moves.into_iter().find(|m| m.is_castle && m.to < m.from);
+        }
+
+        // Promotion suffix: "e8=Q" or "e8Q"
+        let (body, promotion) = match san.char_indices().last() {
+            Some((i, c)) if "NBRQ".contains(c) && i >= 2 => {
+                (san[..i].trim_end_matches('='), piece_from_char(c))
+            }
+            _ => (san, None),
+        };
+
+        let mut chars: Vec<char> = body.chars().filter(|&c| c != 'x' && c != '-').collect();
+        let piece = match chars.first() {
+            Some(&c) if "NBRQK".contains(c) => { chars.remove(0); piece_from_char(c)? }
+            _ => Piece::Pawn,
+        };
+        if chars.len() < 2 { return None; }
+        let to_str: String = chars[chars.len() - 2..].iter().collect();
+        let to = sq_from_str(&to_str)?;
+
+        // Whatever remains is disambiguation: a file, a rank, or both
+        let mut from_file = None;
+        let mut from_rank = None;
+        for &c in &chars[..chars.len() - 2] {
+            match c {
+                'a'..='h' => from_file = Some((c as u8 - b'a') as i32),
+                '1'..='8' => from_rank = Some((c as u8 - b'1') as i32),
+                _ => return None,
+            }
+        }
+
+        let mut found = moves.into_iter().filter(|m| {
+            m.to == to
+                && !m.is_castle
+                && m.promotion == promotion
+                && self.squares[m.from as usize].is_some_and(|cp| cp.piece == piece)
+                && from_file.is_none_or(|f| file_of(m.from) == f)
+                && from_rank.is_none_or(|r| rank_of(m.from) == r)
+        });
+        let mv = found.next()?;
+        if found.next().is_some() { return None; } // ambiguous
+        Some(mv)
+    }
+
+    /// Whether this position plausibly continues the game on `prev`: its
+    /// history passes through `prev`, or it is at most two plies on (a GUI
+    /// sending a bare FEN each move). Anything else is a new game.
+    pub fn continues(&self, prev: &Board) -> bool {
+        if self.hash == prev.hash || self.position_hashes.contains(&prev.hash) {
+            return true;
+        }
+        let mut board = prev.clone();
+        for mv in crate::movegen::generate_moves(&board) {
+            board.make_move(mv);
+            let hit = board.hash == self.hash
+                || crate::movegen::generate_moves(&board).into_iter().any(|reply| {
+                    board.make_move(reply);
+                    let hit = board.hash == self.hash;
+                    board.unmake_move();
+                    hit
+                });
+            board.unmake_move();
+            if hit { return true; }
+        }
         false
     }
 
+    /// Check for threefold repetition. Only positions since the last pawn
+    /// move or capture can recur, so the scan stops there.
+    pub fn is_repetition(&self) -> bool {
+        let current = self.hash;
+        self.reversible_history().iter().filter(|&&h| h == current).count() >= 2
+    }
+
+    /// Hashes of the positions before this one that could still recur:
+    /// everything since the last pawn move or capture, oldest first
+    pub fn reversible_history(&self) -> &[u64] {
+        &self.position_hashes[self.last_reset..]
+    }
+
+    /// Check for 50-move rule. A checkmate delivered on the 100th ply
+    /// stands: mate takes precedence.
+    pub fn is_fifty_move_rule(&self) -> bool {
+        self.halfmove >= 100 && (!self.in_check() || crate::movegen::has_legal_move(self))
+    }
+
+    /// Whether the game has ended on the board: mate, stalemate, threefold
+    /// repetition or the fifty-move rule
+    pub fn is_game_over(&self) -> bool {
+        !crate::movegen::has_legal_move(self) || self.is_fifty_move_rule() || self.is_repetition()
+    }
+}
+
+impl Position {
+    // No pieces, White to move, no rights; from_fen fills it in
+    fn empty() -> Self {
+        Position {
+            squares: [None; 64],
+            side: Color::White,
+            castling: 0,
+            ep_square: None,
+            hash: 0,
+            piece_counts: [[0; 6]; 2],
+            pawn_files:   [[0; 8]; 2],
+            phase_units: 0,
+        }
+    }
+
+    // Recount the incrementally kept material and rehash, from the squares
+    fn refresh(&mut self) {
+        self.piece_counts = [[0; 6]; 2];
+        self.pawn_files = [[0; 8]; 2];
+        self.phase_units = 0;
+        for sq in 0u8..64 {
+            let Some(cp) = self.squares[sq as usize] else { continue };
+            self.piece_counts[cp.color as usize][cp.piece as usize] += 1;
+            if cp.piece == Piece::Pawn {
+                self.pawn_files[cp.color as usize][file_of(sq) as usize] += 1;
+            }
+            self.phase_units += phase_weight(cp.piece);
+        }
+        self.hash = crate::zobrist::keys().hash(self);
+    }
+
+    /// Play `mv` on the position alone: pieces, counts, rights, ep square,
+    /// hash and side to move. A move with no piece on `from` only passes
+    /// the turn. `Board::make_move` adds the clocks and history on top.
+    pub fn make_move(&mut self, mv: Move) {
+        let moving = self.mover(mv);
+        let keys = crate::zobrist::keys();
+        self.hash ^= keys.side();
+
+        let Some(moving) = moving else {
+            self.side = opposite(self.side);
+            return;
+        };
+
+        // Rights and ep square are re-keyed below once their new values are known
+        self.hash ^= keys.castle(self.castling) ^ keys.ep(self.ep_square);
+
+        if mv.is_castle {
+            self.set_square(mv.to, Some(moving));
+            self.set_square(mv.from, None);
+            let (rook_from, rook_to) = if mv.to > mv.from {
+                (mv.from + 3, mv.from + 1)
+            } else {
+                (mv.from.wrapping_sub(4), mv.from.wrapping_sub(1))
+            };
+            if rook_from < 64 && rook_to < 64 {
+                let rook = self.squares[rook_from as usize];
+                self.set_square(rook_to, rook);
+                self.set_square(rook_from, None);
+            }
+        } else {
+            if mv.is_ep {
+                let ep_pawn_sq = if self.side == Color::White {
+                    mv.to.wrapping_sub(8)
+                } else {
+                    mv.to + 8
+                };
+                if ep_pawn_sq < 64 {
+                    self.set_square(ep_pawn_sq, None);
+                }
+            }
+
+            self.set_square(mv.to, if let Some(promo) = mv.promotion {
+                Some(ColoredPiece { piece: promo, color: moving.color })
+            } else {
+                Some(moving)
+            });
+            self.set_square(mv.from, None);
+            self.update_counts(moving.color, mv, moving.piece == Piece::Pawn, false);
+        }
+
+        if matches!(moving.piece, Piece::King) {
+            self.remove_castle(moving.color);
+        }
+        // A rook moving off, or captured on, its home square
+        self.castling &= !(rook_home_right(mv.from) | rook_home_right(mv.to));
+
+        self.ep_square = if matches!(moving.piece, Piece::Pawn) {
+            let diff = (mv.to as i32 - mv.from as i32).abs();
+            if diff == 16 {
+                Some((mv.from + mv.to) / 2)
+            } else { None }
+        } else { None };
+
+        self.hash ^= keys.castle(self.castling) ^ keys.ep(self.ep_square);
+
+        self.side = opposite(self.side);
+    }
+
+    // The piece `mv` moves, if it can be played at all
+    fn mover(&self, mv: Move) -> Option<ColoredPiece> {
+        self.squares.get(mv.from as usize).copied().flatten()
+            .filter(|_| mv.to < 64 && mv.from != mv.to)
+    }
+
+    // Square write that keeps the incremental hash in step
+    fn set_square(&mut self, sq: u8, piece: Option<ColoredPiece>) {
+        let keys = crate::zobrist::keys();
+        if let Some(old) = self.squares[sq as usize] { self.hash ^= keys.piece(old, sq); }
+        if let Some(new) = piece { self.hash ^= keys.piece(new, sq); }
+        self.squares[sq as usize] = piece;
+    }
+
+    // Captures and promotions are the only moves that change piece counts
+    fn update_counts(&mut self, mover: Color, mv: Move, pawn: bool, undo: bool) {
+        let sign = |delta: i8| if undo { -delta } else { delta };
+        let mut adjust = |color: Color, piece: Piece, delta: i8| {
+            let c = &mut self.piece_counts[color as usize][piece as usize];
+            *c = c.saturating_add_signed(sign(delta));
+            self.phase_units += phase_weight(piece) * sign(delta) as i32;
+        };
+        if let Some(cap) = mv.captured { adjust(opposite(mover), cap, -1); }
+        if let Some(promo) = mv.promotion {
+            adjust(mover, Piece::Pawn, -1);
+            adjust(mover, promo, 1);
+        }
+
+        // Pawn files: a captured pawn (en passant too) was on the target
+        // file; a pawn leaves its file by capturing or promoting
+        let (from_file, to_file) = (file_of(mv.from) as usize, file_of(mv.to) as usize);
+        let mut file = |color: Color, f: usize, delta: i8| {
+            let c = &mut self.pawn_files[color as usize][f];
+            *c = c.saturating_add_signed(sign(delta));
+        };
+        if mv.captured == Some(Piece::Pawn) { file(opposite(mover), to_file, -1); }
+        if pawn && (mv.promotion.is_some() || from_file != to_file) {
+            file(mover, from_file, -1);
+            if mv.promotion.is_none() { file(mover, to_file, 1); }
+        }
+    }
+
+    /// Castling rights implied by kings and rooks still on their home
+    /// squares — the default when a FEN omits the castling field, and the
+    /// most a FEN's castling field can grant.
+    fn home_castling(&self) -> u8 {
+        let is = |sq: usize, piece: Piece, color: Color| {
+            self.squares[sq] == Some(ColoredPiece { piece, color })
+        };
+        let mut rights = 0;
+        if is(4, Piece::King, Color::White) {
+            if is(7, Piece::Rook, Color::White) { rights |= WK; }
+            if is(0, Piece::Rook, Color::White) { rights |= WQ; }
+        }
+        if is(60, Piece::King, Color::Black) {
+            if is(63, Piece::Rook, Color::Black) { rights |= BK; }
+            if is(56, Piece::Rook, Color::Black) { rights |= BQ; }
+        }
+        rights
+    }
+
+    pub fn can_castle_ks(&self, color: Color) -> bool {
+        self.castling & castle_bits(color).0 != 0
+    }
+
+    pub fn can_castle_qs(&self, color: Color) -> bool {
+        self.castling & castle_bits(color).1 != 0
+    }
+
+    /// Drop both of `color`'s castling rights (the king has moved)
+    pub fn remove_castle(&mut self, color: Color) {
+        let (ks, qs) = castle_bits(color);
+        self.castling &= !(ks | qs);
+    }
+
+    pub fn piece_at(&self, sq: u8) -> Option<ColoredPiece> {
+        self.squares[sq as usize]
+    }
+
+    pub fn piece_count(&self, color: Color, piece: Piece) -> u8 {
+        self.piece_counts[color as usize][piece as usize]
+    }
+
+    /// Pawns `color` has on `file` (0 = a-file), kept incrementally
+    pub fn pawns_on_file(&self, color: Color, file: i32) -> u8 {
+        self.pawn_files[color as usize][file as usize]
+    }
+
+    /// Game phase from 256 (opening material) down to 0 (bare kings and
+    /// pawns), kept incrementally. Promotions can push material past the
+    /// start, so it is clamped.
+    pub fn phase(&self) -> i32 {
+        (self.phase_units * 256 / PHASE_MAX).clamp(0, 256)
+    }
+
+    /// Material for `color` in centipawns (kings excluded)
+    pub fn material(&self, color: Color) -> i32 {
+        [Piece::Pawn, Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen].iter()
+            .map(|&p| self.piece_count(color, p) as i32 * piece_value(p))
+            .sum()
+    }
+
+    /// Iterate over the legal moves in this position
+    pub fn legal_moves(&self) -> impl Iterator<Item = Move> + '_ {
+        crate::movegen::legal_moves(self)
+    }
+
     pub fn in_check(&self) -> bool {
         let king_sq = self.find_king(self.side);
         king_sq.map(|sq| self.is_attacked(sq, opposite(self.side))).unwrap_or(false)
     }
 
+    /// Whether `mv` would check the opponent, without making it: the move
+    /// is played on a copy of the squares only, then the destination is
+    /// tested for a direct check and each vacated square for a discovered one.
+    pub fn gives_check(&self, mv: Move) -> bool {
+        let Some(moving) = self.squares[mv.from as usize] else { return false };
+        let us = moving.color;
+        let Some(king) = self.find_king(opposite(us)) else { return false };
+
+        let mut squares = self.squares;
+        let placed = mv.promotion.unwrap_or(moving.piece);
+        squares[mv.from as usize] = None;
+        squares[mv.to as usize] = Some(ColoredPiece { piece: placed, color: us });
+
+        if mv.is_castle {
+            // Rare enough to just look at every attacker afterwards
+            let (rook_from, rook_to) = if mv.to > mv.from {
+                (mv.from + 3, mv.from + 1)
+            } else {
+                (mv.from - 4, mv.from - 1)
+            };
+            squares[rook_to as usize] = squares[rook_from as usize].take();
+            return least_valuable_attacker(&squares, king, us).is_some();
+        }
+
+        if mv.is_ep {
+            let cap = if us == Color::White { mv.to - 8 } else { mv.to + 8 };
+            squares[cap as usize] = None;
+            if discovers_on(&squares, cap, king, us) { return true; }
+        }
+        piece_attacks_on(&squares, mv.to, king, placed)
+            || discovers_on(&squares, mv.from, king, us)
+    }
+
+    /// Whether `color`'s king is attacked (false if it has none)
+    pub fn king_attacked(&self, color: Color) -> bool {
+        self.find_king(color)
+            .is_some_and(|sq| least_valuable_attacker(&self.squares, sq, opposite(color)).is_some())
+    }
+
     pub fn find_king(&self, color: Color) -> Option<u8> {
         for sq in 0u8..64 {
             if let Some(cp) = self.squares[sq as usize] {
@@ -321,90 +832,182 @@ impl Board {
     }
 
     fn piece_attacks(&self, from: u8, to: u8, piece: Piece) -> bool {
-        let fr = (from / 8) as i32;
-        let ff = (from % 8) as i32;
-        let tr = (to / 8) as i32;
-        let tf = (to % 8) as i32;
-        let dr = tr - fr;
-        let df = tf - ff;
-
-        match piece {
-            Piece::Pawn => {
-                let dir = if self.squares[from as usize].unwrap().color == Color::White { 1 } else { -1 };
-                dr == dir && df.abs() == 1
-            }
-            Piece::Knight => {
-                (dr.abs() == 2 && df.abs() == 1) || (dr.abs() == 1 && df.abs() == 2)
-            }
-            Piece::Bishop => {
-                dr.abs() == df.abs() && dr != 0 && self.path_clear(from, to)
-            }
-            Piece::Rook => {
-                (dr == 0 || df == 0) && !(dr == 0 && df == 0) && self.path_clear(from, to)
-            }
-            Piece::Queen => {
-                ((dr.abs() == df.abs()) || dr == 0 || df == 0) && !(dr == 0 && df == 0) && self.path_clear(from, to)
-            }
-            Piece::King => {
-                dr.abs() <= 1 && df.abs() <= 1 && !(dr == 0 && df == 0)
-            }
-        }
+        piece_attacks_on(&self.squares, from, to, piece)
     }
 
-    fn path_clear(&self, from: u8, to: u8) -> bool {
-        let fr = (from / 8) as i32;
-        let ff = (from % 8) as i32;
-        let tr = (to / 8) as i32;
-        let tf = (to % 8) as i32;
-        let dr = (tr - fr).signum();
-        let df = (tf - ff).signum();
-        let mut r = fr + dr;
-        let mut f = ff + df;
-        while (r, f) != (tr, tf) {
-            let sq = (r * 8 + f) as u8;
-            if self.squares[sq as usize].is_some() {
-                return false;
-            }
-            r += dr;
-            f += df;
+    /// Static exchange evaluation: material outcome of the capture sequence
+    /// on `mv.to`, both sides always recapturing with their cheapest piece.
+    pub fn see(&self, mv: Move) -> i32 {
+        let Some(moving) = self.squares[mv.from as usize] else { return 0 };
+        let target = mv.to;
+        let mut squares = self.squares;
+        let mut gain = [0i32; 32];
+
+        gain[0] = mv.captured.map(piece_value).unwrap_or(0);
+        let placed = mv.promotion.unwrap_or(moving.piece);
+        if mv.promotion.is_some() { gain[0] += piece_value(placed) - piece_value(Piece::Pawn); }
+        if mv.is_ep {
+            let ep_pawn_sq = if moving.color == Color::White { target - 8 } else { target + 8 };
+            squares[ep_pawn_sq as usize] = None;
         }
-        true
+        squares[mv.from as usize] = None;
+        squares[target as usize] = Some(ColoredPiece { piece: placed, color: moving.color });
+
+        let mut victim = piece_value(placed);
+        let mut side = opposite(moving.color);
+        let mut d = 0;
+        while d + 1 < gain.len() {
+            let Some((from, piece)) = least_valuable_attacker(&squares, target, side) else { break };
+            d += 1;
+            gain[d] = victim - gain[d - 1];
+            // Neither side can improve by continuing
+            if (-gain[d - 1]).max(gain[d]) < 0 { d -= 1; break; }
+            squares[from as usize] = None;
+            squares[target as usize] = Some(ColoredPiece { piece, color: side });
+            victim = piece_value(piece);
+            side = opposite(side);
+        }
+        while d > 0 {
+            gain[d - 1] = -(-gain[d - 1]).max(gain[d]);
+            d -= 1;
+        }
+        gain[0]
     }
 
     pub fn has_non_pawn_material(&self) -> bool {
-        for sq in 0u8..64 {
-            if let Some(cp) = self.squares[sq as usize] {
-                if cp.color == self.side && !matches!(cp.piece, Piece::Pawn | Piece::King) {
-                    return true;
-                }
-            }
+        [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen].iter()
+            .any(|&p| self.piece_count(self.side, p) > 0)
+    }
+}
+
+fn piece_attacks_on(squares: &[Option<ColoredPiece>; 64], from: u8, to: u8, piece: Piece) -> bool {
+    let (fr, ff) = (rank_of(from), file_of(from));
+    let (tr, tf) = (rank_of(to), file_of(to));
+    let dr = tr - fr;
+    let df = tf - ff;
+
+    match piece {
+        Piece::Pawn => {
+            let dir = if squares[from as usize].unwrap().color == Color::White { 1 } else { -1 };
+            dr == dir && df.abs() == 1
+        }
+        Piece::Knight => {
+            (dr.abs() == 2 && df.abs() == 1) || (dr.abs() == 1 && df.abs() == 2)
+        }
+        Piece::Bishop => {
+            dr.abs() == df.abs() && dr != 0 && path_clear_on(squares, from, to)
+        }
+        Piece::Rook => {
+            (dr == 0 || df == 0) && !(dr == 0 && df == 0) && path_clear_on(squares, from, to)
+        }
+        Piece::Queen => {
+            ((dr.abs() == df.abs()) || dr == 0 || df == 0) && !(dr == 0 && df == 0) && path_clear_on(squares, from, to)
+        }
+        Piece::King => {
+            dr.abs() <= 1 && df.abs() <= 1 && !(dr == 0 && df == 0)
         }
-        false
     }
+}
 
-    /// Check for threefold repetition
-    pub fn is_repetition(&self) -> bool {
-        let current = self.hash;
-        let count = self.position_hashes.iter().filter(|&&h| h == current).count();
-        count >= 2
+// Is `king` attacked by a `by` slider along the line through the vacated
+// square `through`? The first piece past the king on that line decides.
+fn discovers_on(squares: &[Option<ColoredPiece>; 64], through: u8, king: u8, by: Color) -> bool {
+    let (kr, kf) = (rank_of(king), file_of(king));
+    let (dr, df) = (rank_of(through) - kr, file_of(through) - kf);
+    let diagonal = dr.abs() == df.abs();
+    if !(diagonal || dr == 0 || df == 0) || (dr, df) == (0, 0) { return false; }
+
+    let (sr, sf) = (dr.signum(), df.signum());
+    let (mut r, mut f) = (kr + sr, kf + sf);
+    while r >= 0 && r < 8 && f >= 0 && f < 8 {
+        if let Some(cp) = squares[make_sq(f, r) as usize] {
+            return cp.color == by && match cp.piece {
+                Piece::Queen  => true,
+                Piece::Bishop => diagonal,
+                Piece::Rook   => !diagonal,
+                _ => false,
+            };
+        }
+        r += sr;
+        f += sf;
     }
+    false
+}
 
-    /// Check for 50-move rule
-    pub fn is_fifty_move_rule(&self) -> bool {
-        self.halfmove >= 100
+fn path_clear_on(squares: &[Option<ColoredPiece>; 64], from: u8, to: u8) -> bool {
+    let (fr, ff) = (rank_of(from), file_of(from));
+    let (tr, tf) = (rank_of(to), file_of(to));
+    let dr = (tr - fr).signum();
+    let df = (tf - ff).signum();
+    let mut r = fr + dr;
+    let mut f = ff + df;
+    while (r, f) != (tr, tf) {
+        if squares[make_sq(f, r) as usize].is_some() {
+            return false;
+        }
+        r += dr;
+        f += df;
     }
+    true
+}
+
+// Cheapest piece of `by` attacking `sq` (used by SEE)
+fn least_valuable_attacker(squares: &[Option<ColoredPiece>; 64], sq: u8, by: Color) -> Option<(u8, Piece)> {
+    (0u8..64)
+        .filter_map(|from| squares[from as usize].map(|cp| (from, cp)))
+        .filter(|&(from, cp)| cp.color == by && from != sq && piece_attacks_on(squares, from, sq, cp.piece))
+        .min_by_key(|&(_, cp)| piece_value(cp.piece))
+        .map(|(from, cp)| (from, cp.piece))
 }
 
 pub fn opposite(c: Color) -> Color {
     match c { Color::White => Color::Black, Color::Black => Color::White }
 }
 
+// Squares run a1 = 0, b1 = 1 ... h8 = 63. Files and ranks are i32 so
+// geometry (deltas, walking off the board) needs no further casts.
+
+#[inline]
+pub fn file_of(sq: u8) -> i32 {
+    (sq % 8) as i32
+}
+
+#[inline]
+pub fn rank_of(sq: u8) -> i32 {
+    (sq / 8) as i32
+}
+
+/// The square at `file`, `rank` (both 0..8)
+#[inline]
+pub fn make_sq(file: i32, rank: i32) -> u8 {
+    (rank * 8 + file) as u8
+}
+
+pub fn sq_to_str(sq: u8) -> String {
+    format!("{}{}", (b'a' + file_of(sq) as u8) as char, rank_of(sq) + 1)
+}
+
 pub fn sq_from_str(s: &str) -> Option<u8> {
     let bytes = s.as_bytes();
     if bytes.len() < 2 { return None; }
     let file = bytes[0].wrapping_sub(b'a');
     let rank = bytes[1].wrapping_sub(b'1');
-    if file < 8 && rank < 8 { Some(rank * 8 + file) } else { None }
+    if file < 8 && rank < 8 { Some(make_sq(file as i32, rank as i32)) } else { None }
+}
+
+fn piece_char(p: Piece) -> char {
+    match p {
+        Piece::Pawn => 'P', Piece::Knight => 'N', Piece::Bishop => 'B',
+        Piece::Rook => 'R', Piece::Queen  => 'Q', Piece::King   => 'K',
+    }
+}
+
+fn piece_from_char(c: char) -> Option<Piece> {
+    match c.to_ascii_uppercase() {
+        'P' => Some(Piece::Pawn),   'N' => Some(Piece::Knight), 'B' => Some(Piece::Bishop),
+        'R' => Some(Piece::Rook),   'Q' => Some(Piece::Queen),  'K' => Some(Piece::King),
+        _ => None,
+    }
 }
 
 pub fn piece_value(p: Piece) -> i32 {
@@ -416,4 +1019,35 @@ pub fn piece_value(p: Piece) -> i32 {
         Piece::Queen  => 900,
         Piece::King   => 20000,
     }
-}
\ No newline at end of file
+}
+
+/// (kingside, queenside) castling bits for a color
+pub fn castle_bits(color: Color) -> (u8, u8) {
+    match color {
+        Color::White => (WK, WQ),
+        Color::Black => (BK, BQ),
+    }
+}
+
+/// The right that is lost when a rook leaves (or is captured on) `sq`
+fn rook_home_right(sq: u8) -> u8 {
+    match sq {
+        0  => WQ,
+        7  => WK,
+        56 => BQ,
+        63 => BK,
+        _  => 0,
+    }
+}
+
+/// Phase units in the starting position: 8 minors x1 + 4 rooks x2 + 2 queens x4
+pub const PHASE_MAX: i32 = 8 + 4 * 2 + 2 * 4;
+
+fn phase_weight(p: Piece) -> i32 {
+    match p {
+        Piece::Knight | Piece::Bishop => 1,
+        Piece::Rook  => 2,
+        Piece::Queen => 4,
+        Piece::Pawn | Piece::King => 0,
+    }
+}
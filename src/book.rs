@@ -1,6 +1,190 @@
-// book.rs — Disabled for now (was causing illegal moves)
-use crate::board::Board;
-
-pub fn lookup(_board: &Board) -> Option<String> {
-    None // Just use engine search, it's strong enough!
-}
\ No newline at end of file
+// book.rs — Small built-in opening book with seedable weighted-random selection
+//
+// The book used to hand back raw UCI strings, which is how illegal moves got
+// played. Every candidate is now resolved against generate_moves before use.
+//
+// Moves are kept per position, not per line, so lines (or PGN games) that
+// transpose into the same position pool their weights there.
+
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::board::{Board, Move};
+use crate::movegen::generate_moves;
+use crate::zobrist::Zobrist;
+
+// Each line is replayed from the start position; every move along it is
+// recorded (with the line's weight) for the position it was played from.
+const LINES: &[(&str, u32)] = &[
+    ("e2e4 e7e5 g1f3 b8c6 f1b5 a7a6", 10),
+    ("e2e4 e7e5 g1f3 b8c6 f1c4 f8c5", 6),
+    ("e2e4 c7c5 g1f3 d7d6 d2d4 c5d4", 8),
+    ("e2e4 c7c5 g1f3 b8c6 d2d4 c5d4", 5),
+    ("e2e4 e7e6 d2d4 d7d5 b1c3", 6),
+    ("e2e4 c7c6 d2d4 d7d5 b1c3", 5),
+    ("d2d4 d7d5 c2c4 e7e6 b1c3 g8f6", 8),
+    ("d2d4 d7d5 c2c4 c7c6 g1f3 g8f6", 6),
+    ("d2d4 g8f6 c2c4 e7e6 b1c3 f8b4", 8),
+    ("d2d4 g8f6 c2c4 g7g6 b1c3 f8g7", 5),
+    ("c2c4 e7e5 b1c3 g8f6", 4),
+    ("g1f3 d7d5 d2d4 g8f6", 4),
+];
+
+const PGN_BOOK_PLIES: usize = 24; // moves of each PGN game that go into the book
+
+pub struct Book {
+    pub enabled: bool,
+    entries: HashMap<u64, Vec<(String, u32)>>,
+    zob:     Zobrist,
+    rng:     StdRng,
+    seed:    u64,
+}
+
+impl Book {
+    /// Builds the book with a time-based seed.
+    pub fn new() -> Self {
+        Book::with_seed(time_seed())
+    }
+
+    /// Builds the book with a fixed seed, so the same position always
+    /// yields the same sequence of book choices.
+    pub fn with_seed(seed: u64) -> Self {
+        let mut book = Book::empty(seed);
+        for &(line, weight) in LINES {
+            let mut board = Board::start_pos();
+            for uci in line.split_whitespace() {
+                book.add(&board, uci, weight);
+                if !board.make_uci_move(uci) { break; }
+            }
+        }
+        book
+    }
+
+    /// Builds a book from the games in a PGN file: the first
+    /// PGN_BOOK_PLIES moves of each game, one count per game. Games may
+    /// reach a position by different move orders; its moves are counted
+    /// across all of them. A game stops at a move that can't be played.
+    pub fn build_from_pgn(pgn: &str, seed: u64) -> Self {
+        let mut book = Book::empty(seed);
+        for game in pgn_games(pgn) {
+            let mut board = Board::start_pos();
+            for san in game.iter().take(PGN_BOOK_PLIES) {
+                let Some(mv) = board.parse_san(san) else { break };
+                book.add(&board, &mv.to_uci(), 1);
+                board.make_move(mv);
+            }
+        }
+        book
+    }
+
+    fn empty(seed: u64) -> Self {
+        Book { enabled: true, entries: HashMap::new(), zob: Zobrist::new(),
+               rng: StdRng::seed_from_u64(seed), seed }
+    }
+
+    fn add(&mut self, board: &Board, uci: &str, weight: u32) {
+        let cands = self.entries.entry(self.key(board)).or_default();
+        match cands.iter_mut().find(|(m, _)| m == uci) {
+            Some(c) => c.1 += weight,
+            None    => cands.push((uci.to_string(), weight)),
+        }
+    }
+
+    // The hash, less an en passant square no pawn can take on: a double
+    // step otherwise keeps two move orders of the same position apart
+    fn key(&self, board: &Board) -> u64 {
+        let hash = self.zob.hash(board);
+        match board.ep_square {
+            Some(_) if !generate_moves(board).iter().any(|m| m.is_ep) =>
+                hash ^ self.zob.ep(board.ep_square),
+            _ => hash,
+        }
+    }
+
+    /// Book moves (UCI) for this position with their weights
+    pub fn candidates(&self, board: &Board) -> &[(String, u32)] {
+        self.entries.get(&self.key(board)).map_or(&[], |c| c.as_slice())
+    }
+
+    /// Reseeds the selection RNG. A seed of 0 means "use the clock".
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = if seed == 0 { time_seed() } else { seed };
+        self.rng = StdRng::seed_from_u64(self.seed);
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Picks a weighted-random legal book move for this position, if any.
+    pub fn probe(&mut self, board: &Board) -> Option<Move> {
+        if !self.enabled { return None; }
+        let cands = self.entries.get(&self.key(board))?;
+
+        let legal = generate_moves(board);
+        let playable: Vec<(Move, u32)> = cands.iter()
+            .filter_map(|(uci, w)| legal.iter().find(|m| m.to_uci() == *uci).map(|&m| (m, *w)))
+            .collect();
+
+        let total: u32 = playable.iter().map(|&(_, w)| w).sum();
+        if total == 0 { return None; }
+
+        let mut pick = self.rng.gen_range(0..total);
+        for (mv, w) in playable {
+            if pick < w { return Some(mv); }
+            pick -= w;
+        }
+        None
+    }
+}
+
+impl Default for Book {
+    fn default() -> Self { Self::new() }
+}
+
+pub(crate) fn time_seed() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0x9e37_79b9_7f4a_7c15)
+        .max(1)
+}
+
+// SAN moves of each game in a PGN text. Tags, comments, variations, NAGs,
+// move numbers and results are skipped; a result ends the game.
+fn pgn_games(pgn: &str) -> Vec<Vec<String>> {
+    let mut games = Vec::new();
+    let mut moves: Vec<String> = Vec::new();
+    let (mut comment, mut variation) = (false, 0);
+    for line in pgn.lines() {
+        let line = line.trim();
+        if !comment && (line.starts_with('[') || line.starts_with('%')) { continue; }
+        let line = if comment { line } else { line.split(';').next().unwrap_or("") };
+        let spaced = line.replace('{', " { ").replace('}', " } ")
+            .replace('(', " ( ").replace(')', " ) ");
+        for tok in spaced.split_whitespace() {
+            match tok {
+                "{" => comment = true,
+                "}" => comment = false,
+                _ if comment => {}
+                "(" => variation += 1,
+                ")" => variation = (variation - 1).max(0),
+                _ if variation > 0 => {}
+                "1-0" | "0-1" | "1/2-1/2" | "*" => {
+                    if !moves.is_empty() { games.push(std::mem::take(&mut moves)); }
+                }
+                _ if tok.starts_with('$') => {}
+                _ => {
+                    // "12." / "12..." prefixes, possibly glued to the move
+                    let san = tok.rsplit('.').next().unwrap_or("");
+                    let san = san.trim_end_matches(['!', '?']);
+                    if !san.is_empty() { moves.push(san.to_string()); }
+                }
+            }
+        }
+    }
+    if !moves.is_empty() { games.push(moves); }
+    games
+}
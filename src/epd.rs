@@ -0,0 +1,64 @@
+// epd.rs — EPD records (FEN position + operations) for tactical test suites
+
+use std::collections::HashMap;
+
+use crate::board::{Board, Move};
+
+/// Parses an EPD line: the four position fields of a FEN followed by
+/// `opcode operand...;` operations (`bm Qxf7+; id "WAC.001";`). Operand
+/// quotes are stripped; clocks default to "0 1" unless `hmvc`/`fmvn` say
+/// otherwise.
+pub fn parse_epd(line: &str) -> (Board, HashMap<String, String>) {
+    let line = line.trim();
+
+    // The position is the first four fields; operations start after them
+    let mut rest = line;
+    let mut fields = Vec::with_capacity(4);
+    for _ in 0..4 {
+        rest = rest.trim_start();
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        if end == 0 { break; }
+        fields.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+
+    let mut ops = HashMap::new();
+    for op in split_ops(rest) {
+        let op = op.trim();
+        if op.is_empty() { continue; }
+        let (code, operand) = op.split_once(char::is_whitespace).unwrap_or((op, ""));
+        let operand = operand.trim().trim_matches('"');
+        ops.insert(code.to_string(), operand.to_string());
+    }
+
+    let halfmove = ops.get("hmvc").map_or("0", |s| s.as_str());
+    let fullmove = ops.get("fmvn").map_or("1", |s| s.as_str());
+    let board = Board::from_fen(&format!("{} {} {}", fields.join(" "), halfmove, fullmove));
+    (board, ops)
+}
+
+/// Resolves a move-list operation (`bm`, `am`) against the position. Moves
+/// are SAN per the EPD spec; UCI is accepted too. Unresolvable ones are dropped.
+pub fn epd_moves(board: &Board, ops: &HashMap<String, String>, key: &str) -> Vec<Move> {
+    ops.get(key).map_or_else(Vec::new, |list| {
+        list.split_whitespace()
+            .filter_map(|s| board.parse_uci(s).or_else(|| board.parse_san(s)))
+            .collect()
+    })
+}
+
+// Splits on ';' outside double quotes
+fn split_ops(s: &str) -> Vec<&str> {
+    let mut ops = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => { ops.push(&s[start..i]); start = i + 1; }
+            _ => {}
+        }
+    }
+    ops.push(&s[start..]);
+    ops
+}
@@ -2,15 +2,21 @@
 //
 // Key improvements:
 //  • Game phase blending — PSTs smoothly shift opening→endgame
-//  • Queen penalised for early development
+//  • Queen penalised for early development, more so with minors still at home
 //  • Knights don't rush out before centre established
 //  • King safety — penalise exposed king in middlegame
 //  • Pawn structure — doubled/isolated penalties
-//  • Bishop pair bonus
-//  • Rook on open file / 7th rank bonus
+//  • Bishop pair bonus, growing as pawns come off
+//  • Rook on open file; rook/queen on the 7th when it hems in the king
+//  • Rooks and queens closing in on the enemy king in the endgame
 //  • Mobility bonus
+//  • Passed pawns — connected passers, and the pawn race in pawn endings
+//  • Wrong-bishop rook-pawn fortress recognised as a draw
+//
+// `eval_features` returns the terms unweighted; `EvalWeights::default()`
+// dotted with them is `evaluate_white`, so a tuner can fit the weights.
 
-use crate::board::{Board, Color, Piece, opposite};
+use crate::board::{Color, ColoredPiece, Piece, Position, file_of, make_sq, rank_of};
 
 const VAL_PAWN:   i32 = 100;
 const VAL_KNIGHT: i32 = 320;
@@ -18,6 +24,10 @@ const VAL_BISHOP: i32 = 340;
 const VAL_ROOK:   i32 = 500;
 const VAL_QUEEN:  i32 = 950;
 
+// Bonus for having the move; only in the side-relative `evaluate`, so a null
+// move swings the score by exactly twice this
+pub const TEMPO: i32 = 10;
+
 // ── PSTs: opening and endgame per piece ─────────────────────────────────────
 // Rank 0 = rank 1 for white. Black mirrors: sq ^ 56
 
@@ -152,19 +162,9 @@ const KING_EG: [i32; 64] = [
 
 // ── Phase (256=opening, 0=endgame) ──────────────────────────────────────────
 
-fn game_phase(board: &Board) -> i32 {
-    let mut mat = 0;
-    for sq in 0u8..64 {
-        if let Some(cp) = board.squares[sq as usize] {
-            mat += match cp.piece {
-                Piece::Knight | Piece::Bishop => 1,
-                Piece::Rook  => 2,
-                Piece::Queen => 4,
-                _ => 0,
-            };
-        }
-    }
-    ((mat * 256) / 28).min(256)
+/// Game phase, 256 (opening) down to 0 (endgame); see `Position::phase`
+pub fn game_phase(board: &Position) -> i32 {
+    board.phase()
 }
 
 fn pst_blend(sq: u8, color: Color, op: &[i32;64], eg: &[i32;64], phase: i32) -> i32 {
@@ -174,15 +174,8 @@ fn pst_blend(sq: u8, color: Color, op: &[i32;64], eg: &[i32;64], phase: i32) ->
 
 // ── Pawn structure ───────────────────────────────────────────────────────────
 
-fn pawn_structure(board: &Board, color: Color) -> i32 {
-    let mut file_cnt = [0u8; 8];
-    for sq in 0u8..64 {
-        if let Some(cp) = board.squares[sq as usize] {
-            if cp.piece == Piece::Pawn && cp.color == color {
-                file_cnt[(sq % 8) as usize] += 1;
-            }
-        }
-    }
+fn pawn_structure(board: &Position, color: Color) -> i32 {
+    let file_cnt: [u8; 8] = std::array::from_fn(|f| board.pawns_on_file(color, f as i32));
     let mut score = 0;
     for f in 0..8usize {
         if file_cnt[f] == 0 { continue; }
@@ -195,125 +188,450 @@ fn pawn_structure(board: &Board, color: Color) -> i32 {
 
 // ── King safety ──────────────────────────────────────────────────────────────
 
-fn king_safety(board: &Board, color: Color, phase: i32) -> i32 {
+const KING_ZONE_ATTACK: i32 = 6; // per square next to the king the enemy attacks
+
+/// The squares a king on `sq` steps to
+pub fn king_zone(sq: u8) -> u64 {
+    let (f, r) = (file_of(sq), rank_of(sq));
+    KING_STEPS.iter()
+        .filter(|&&(dr, df)| r + dr >= 0 && r + dr < 8 && f + df >= 0 && f + df < 8)
+        .fold(0, |zone, &(dr, df)| zone | 1 << make_sq(f + df, r + dr))
+}
+
+// `enemy` is the other side's attack map from this evaluate() call
+fn king_safety(board: &Position, color: Color, phase: i32, enemy: &Attacks) -> i32 {
     if phase < 60 { return 0; }
     let king_sq = match board.find_king(color) { Some(s) => s, None => return 0 };
-    let kf = (king_sq % 8) as i32;
+    let kf = file_of(king_sq);
     let mut score = 0;
     // Open files near king
     for df in -1i32..=1 {
         let f = kf + df;
         if f < 0 || f >= 8 { continue; }
-        let has_pawn = (0u8..8).any(|r| {
-            board.squares[(r*8+f as u8) as usize]
-                .map_or(false, |cp| cp.piece == Piece::Pawn && cp.color == color)
-        });
-        if !has_pawn { score -= 18 * phase / 256; }
+        if board.pawns_on_file(color, f) == 0 { score -= 18 * phase / 256; }
     }
     // King in centre penalty
     if kf >= 2 && kf <= 5 { score -= 22 * phase / 256; }
+    let pressure = (king_zone(king_sq) & enemy.squares).count_ones() as i32;
+    score -= KING_ZONE_ATTACK * pressure * phase / 256;
+    score + pawn_shield(board, color, king_sq) * phase / 256
+}
+
+// Shield pawns in front of a castled (wing, back-rank) king: one step
+// ahead is ideal, two steps is a weakened shield, otherwise it's missing.
+fn pawn_shield(board: &Position, color: Color, king_sq: u8) -> i32 {
+    let (kf, kr) = (file_of(king_sq), rank_of(king_sq));
+    let (back, dir) = if color == Color::White { (0, 1) } else { (7, -1) };
+    if kr != back || (kf >= 2 && kf <= 5) { return 0; }
+
+    let own_pawn = |f: i32, r: i32| {
+        board.squares[make_sq(f, r) as usize]
+            .is_some_and(|cp| cp.piece == Piece::Pawn && cp.color == color)
+    };
+    let mut score = 0;
+    for f in (kf - 1).max(0)..=(kf + 1).min(7) {
+        if own_pawn(f, back + dir) {
+            continue;
+        } else if own_pawn(f, back + 2 * dir) {
+            score -= 10; // pushed one square
+        } else {
+            score -= 25; // missing or pushed further
+        }
+    }
     score
 }
 
 // ── Bishop pair ──────────────────────────────────────────────────────────────
 
-fn bishop_pair(board: &Board, color: Color) -> i32 {
-    let n = board.squares.iter()
-        .filter_map(|s| *s)
-        .filter(|cp| cp.color == color && cp.piece == Piece::Bishop)
-        .count();
-    if n >= 2 { 30 } else { 0 }
+const BISHOP_PAIR_BASE: i32 = 20;     // with all sixteen pawns on the board
+const BISHOP_PAIR_PER_PAWN: i32 = 2;  // for each pawn gone: open lines suit bishops
+
+pub fn bishop_pair(board: &Position, color: Color) -> i32 {
+    if board.piece_count(color, Piece::Bishop) < 2 { return 0; }
+    let pawns = (board.piece_count(Color::White, Piece::Pawn) + board.piece_count(Color::Black, Piece::Pawn)) as i32;
+    BISHOP_PAIR_BASE + BISHOP_PAIR_PER_PAWN * (16 - pawns).max(0)
+}
+
+// ── Queen development ────────────────────────────────────────────────────────
+
+const QUEEN_SORTIE: i32 = 10; // per minor piece still at home, full opening phase
+
+// A queen off its home square while knights and bishops haven't moved yet:
+// the minors stay boxed in and the queen becomes a target for them
+fn queen_sortie(board: &Position, color: Color, phase: i32) -> i32 {
+    let back = if color == Color::White { 0 } else { 7 };
+    let at = |f: i32, piece: Piece| board.squares[make_sq(f, back) as usize] == Some(ColoredPiece { piece, color });
+    if board.piece_count(color, Piece::Queen) == 0 || at(3, Piece::Queen) { return 0; }
+    let home_minors = [(1, Piece::Knight), (2, Piece::Bishop), (5, Piece::Bishop), (6, Piece::Knight)]
+        .iter().filter(|&&(f, p)| at(f, p)).count() as i32;
+    -QUEEN_SORTIE * home_minors * phase / 256
 }
 
 // ── Rook bonuses ─────────────────────────────────────────────────────────────
 
-fn rook_bonus(board: &Board, color: Color) -> i32 {
+fn rook_bonus(board: &Position, color: Color) -> i32 {
     let mut score = 0;
-    let seventh = if color == Color::White { 6u8 } else { 1u8 };
     for sq in 0u8..64 {
         let Some(cp) = board.squares[sq as usize] else { continue };
         if cp.color != color || cp.piece != Piece::Rook { continue; }
-        let file = sq % 8;
-        let friendly = (0u8..8).any(|r| board.squares[(r*8+file) as usize]
-            .map_or(false, |p| p.piece == Piece::Pawn && p.color == color));
-        let enemy = (0u8..8).any(|r| board.squares[(r*8+file) as usize]
-            .map_or(false, |p| p.piece == Piece::Pawn && p.color != color));
+        let file = file_of(sq);
+        let enemy_color = if color == Color::White { Color::Black } else { Color::White };
+        let friendly = board.pawns_on_file(color, file) > 0;
+        let enemy = board.pawns_on_file(enemy_color, file) > 0;
         if !friendly && !enemy { score += 20; }
         else if !friendly      { score += 10; }
-        if sq / 8 == seventh   { score += 25; }
     }
     score
 }
 
-// ── Mobility ─────────────────────────────────────────────────────────────────
+const ROOK_ON_SEVENTH: i32 = 25;
+const QUEEN_ON_SEVENTH: i32 = 10;
+
+// A rook or queen on the 7th only counts when it confines the enemy king to
+// its back rank or has enemy pawns there to attack
+fn seventh_rank(board: &Position, color: Color) -> i32 {
+    let enemy = if color == Color::White { Color::Black } else { Color::White };
+    let (seventh, eighth) = if color == Color::White { (6, 7) } else { (1, 0) };
+    let king_confined = board.find_king(enemy).is_some_and(|k| rank_of(k) == eighth);
+    let pawns = (0..8).any(|f| board.squares[make_sq(f, seventh) as usize]
+        == Some(ColoredPiece { piece: Piece::Pawn, color: enemy }));
+    if !king_confined && !pawns { return 0; }
+
+    (0..8).filter_map(|f| board.squares[make_sq(f, seventh) as usize])
+        .filter(|cp| cp.color == color)
+        .map(|cp| match cp.piece {
+            Piece::Rook  => ROOK_ON_SEVENTH,
+            Piece::Queen => QUEEN_ON_SEVENTH,
+            _ => 0,
+        })
+        .sum()
+}
+
+// ── Endgame king tropism ─────────────────────────────────────────────────────
+
+const ROOK_TROPISM: i32 = 2;  // per square closer to the enemy king (of 7)
+const QUEEN_TROPISM: i32 = 3;
+
+// Rooks and queens drawing in on the enemy king: mating nets and cut-off
+// kings. Fades out with the middlegame, where king safety takes over
+fn king_tropism(board: &Position, color: Color, phase: i32) -> i32 {
+    let enemy = if color == Color::White { Color::Black } else { Color::White };
+    let Some(king) = board.find_king(enemy) else { return 0 };
+    let mut score = 0;
+    for sq in 0u8..64 {
+        let Some(cp) = board.squares[sq as usize] else { continue };
+        if cp.color != color { continue; }
+        let weight = match cp.piece {
+            Piece::Rook  => ROOK_TROPISM,
+            Piece::Queen => QUEEN_TROPISM,
+            _ => continue,
+        };
+        let dist = (file_of(sq) - file_of(king)).abs().max((rank_of(sq) - rank_of(king)).abs());
+        score += weight * (7 - dist);
+    }
+    score * (256 - phase) / 256
+}
+
+// ── Passed pawns ─────────────────────────────────────────────────────────────
+
+const CONNECTED_PASSER: i32 = 12;   // per rank advanced, pawn beside or behind a passer
+const UNSTOPPABLE_PASSER: i32 = 500; // passer the king can't catch, no pieces to stop it
+
+// No enemy pawn ahead on its own or an adjacent file
+fn is_passed(board: &Position, sq: u8, color: Color) -> bool {
+    let (file, rank) = (file_of(sq), rank_of(sq));
+    let ahead = |r: i32| if color == Color::White { r > rank } else { r < rank };
+    (file - 1..=file + 1).filter(|&f| f >= 0 && f < 8).all(|f| {
+        (0..8).filter(|&r| ahead(r)).all(|r| {
+            board.squares[make_sq(f, r) as usize]
+                .is_none_or(|cp| cp.piece != Piece::Pawn || cp.color == color)
+        })
+    })
+}
+
+fn passed_pawns(board: &Position, color: Color, phase: i32) -> i32 {
+    let enemy = if color == Color::White { Color::Black } else { Color::White };
+    let promo_rank = if color == Color::White { 7 } else { 0 };
+    let advance = |sq: u8| if color == Color::White { rank_of(sq) } else { 7 - rank_of(sq) };
+    let own_pawn = |f: i32, r: i32| f >= 0 && f < 8 && r >= 0 && r < 8
+        && board.squares[make_sq(f, r) as usize] == Some(ColoredPiece { piece: Piece::Pawn, color });
+
+    // The square rule only holds when nothing but the king can stop a pawn
+    let enemy_pieces = [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen].iter()
+        .any(|&p| board.piece_count(enemy, p) > 0);
+    let enemy_king = board.find_king(enemy);
+
+    let mut connected = 0;
+    let mut unstoppable = false;
+    for sq in 0u8..64 {
+        if board.squares[sq as usize] != Some(ColoredPiece { piece: Piece::Pawn, color }) { continue; }
+        if !is_passed(board, sq, color) { continue; }
+        let (file, rank) = (file_of(sq), rank_of(sq));
+
+        // A pawn level with or one behind on an adjacent file guards the advance
+        let back = if color == Color::White { -1 } else { 1 };
+        if [-1, 1].iter().any(|&df| own_pawn(file + df, rank) || own_pawn(file + df, rank + back)) {
+            connected += CONNECTED_PASSER * advance(sq);
+        }
+
+        if enemy_pieces || unstoppable { continue; }
+        let Some(king) = enemy_king else { continue };
+        // The path must be clear; the double step counts from the home rank
+        let path_clear = (1..=(promo_rank - rank).abs()).all(|i| {
+            board.squares[make_sq(file, rank - back * i) as usize].is_none()
+        });
+        if !path_clear { continue; }
+        let mut pawn_moves = 7 - advance(sq);
+        if advance(sq) == 1 { pawn_moves -= 1; }
+        let king_moves = (file_of(king) - file).abs().max((rank_of(king) - promo_rank).abs());
+        let defender_to_move = board.side == enemy;
+        if king_moves - i32::from(defender_to_move) > pawn_moves {
+            unstoppable = true;
+        }
+    }
+    connected * (256 - phase) / 256 + if unstoppable { UNSTOPPABLE_PASSER } else { 0 }
+}
+
+// ── Attack maps ──────────────────────────────────────────────────────────────
+
+const KNIGHT_STEPS: [(i32,i32); 8] = [(-2,-1),(-2,1),(-1,-2),(-1,2),(1,-2),(1,2),(2,-1),(2,1)];
+const KING_STEPS:   [(i32,i32); 8] = [(-1,-1),(-1,0),(-1,1),(0,-1),(0,1),(1,-1),(1,0),(1,1)];
+const DIAGONALS:    [(i32,i32); 4] = [(-1,-1),(-1,1),(1,-1),(1,1)];
+const ORTHOGONALS:  [(i32,i32); 4] = [(-1,0),(1,0),(0,-1),(0,1)];
 
-fn mobility(board: &Board, color: Color) -> i32 {
-    let mut count = 0i32;
+/// One side's attacks, generated once per evaluate() call and shared by the
+/// terms that need them
+pub struct Attacks {
+    pub squares: u64,  // bit per square attacked (own pieces defended included)
+    pub mobility: i32, // knight/bishop/rook/queen moves to empty or enemy squares
+}
+
+pub fn attacks(board: &Position, color: Color) -> Attacks {
+    let mut a = Attacks { squares: 0, mobility: 0 };
     for from in 0u8..64 {
         let Some(cp) = board.squares[from as usize] else { continue };
         if cp.color != color { continue; }
-        let (fr,ff) = ((from/8) as i32, (from%8) as i32);
         match cp.piece {
-            Piece::Knight => {
-                for (dr,df) in [(-2,-1),(-2,1),(-1,-2),(-1,2),(1,-2),(1,2),(2,-1),(2,1)] {
-                    let (tr,tf)=(fr+dr,ff+df);
-                    if tr>=0&&tr<8&&tf>=0&&tf<8 {
-                        let to=(tr*8+tf) as u8;
-                        if board.squares[to as usize].map_or(true,|c|c.color!=color){count+=1;}
-                    }
-                }
+            Piece::Pawn => {
+                let dr = if color == Color::White { 1 } else { -1 };
+                a.steps(board, from, color, &[(dr,-1),(dr,1)], false);
             }
-            Piece::Bishop => count += slider_mob(board,from,color,&[(-1,-1),(-1,1),(1,-1),(1,1)]),
-            Piece::Rook   => count += slider_mob(board,from,color,&[(-1,0),(1,0),(0,-1),(0,1)]),
+            Piece::Knight => a.steps(board, from, color, &KNIGHT_STEPS, true),
+            Piece::Bishop => a.rays(board, from, color, &DIAGONALS),
+            Piece::Rook   => a.rays(board, from, color, &ORTHOGONALS),
             Piece::Queen  => {
-                count += slider_mob(board,from,color,&[(-1,-1),(-1,1),(1,-1),(1,1)]);
-                count += slider_mob(board,from,color,&[(-1,0),(1,0),(0,-1),(0,1)]);
+                a.rays(board, from, color, &DIAGONALS);
+                a.rays(board, from, color, &ORTHOGONALS);
+            }
+            Piece::King => a.steps(board, from, color, &KING_STEPS, false),
+        }
+    }
+    a
+}
+
+impl Attacks {
+    fn steps(&mut self, board: &Position, from: u8, color: Color, steps: &[(i32,i32)], mobile: bool) {
+        let (fr,ff) = (rank_of(from), file_of(from));
+        for &(dr,df) in steps {
+            let (tr,tf) = (fr+dr,ff+df);
+            if tr>=0&&tr<8&&tf>=0&&tf<8 {
+                let to = make_sq(tf,tr);
+                self.squares |= 1 << to;
+                if mobile && board.squares[to as usize].is_none_or(|c|c.color!=color) { self.mobility += 1; }
+            }
+        }
+    }
+
+    fn rays(&mut self, board: &Position, from: u8, color: Color, dirs: &[(i32,i32)]) {
+        let (fr,ff) = (rank_of(from), file_of(from));
+        for &(dr,df) in dirs {
+            let (mut tr,mut tf) = (fr+dr,ff+df);
+            while tr>=0&&tr<8&&tf>=0&&tf<8 {
+                let to = make_sq(tf,tr);
+                self.squares |= 1 << to;
+                if let Some(cp)=board.squares[to as usize] { if cp.color!=color{self.mobility+=1;} break; }
+                self.mobility+=1; tr+=dr; tf+=df;
             }
-            _ => {}
         }
     }
-    count
 }
 
-fn slider_mob(board: &Board, from: u8, color: Color, dirs: &[(i32,i32)]) -> i32 {
-    let mut n = 0;
-    let (fr,ff) = ((from/8) as i32, (from%8) as i32);
-    for &(dr,df) in dirs {
-        let (mut tr,mut tf) = (fr+dr,ff+df);
-        while tr>=0&&tr<8&&tf>=0&&tf<8 {
-            let to=(tr*8+tf) as u8;
-            if let Some(cp)=board.squares[to as usize] { if cp.color!=color{n+=1;} break; }
-            n+=1; tr+=dr; tf+=df;
+// ── Threats ─────────────────────────────────────────────────────────────────
+
+const HANGING_PIECE: i32 = 15; // per enemy piece we attack that nothing defends
+
+// Knights, bishops, rooks and queens of `color`'s opponent left en prise,
+// from both sides' attack maps of this evaluate() call
+fn threats(board: &Position, color: Color, own: &Attacks, enemy: &Attacks) -> i32 {
+    let hanging = own.squares & !enemy.squares;
+    (0u8..64).filter(|&sq| hanging >> sq & 1 == 1)
+        .filter(|&sq| board.squares[sq as usize].is_some_and(|cp| {
+            cp.color != color && cp.piece != Piece::Pawn && cp.piece != Piece::King
+        }))
+        .count() as i32 * HANGING_PIECE
+}
+
+// ── Drawn endings ───────────────────────────────────────────────────────────
+
+// King, bishop and rook pawn(s) on one file against a bare king sitting at
+// the promotion corner, the bishop on the other colour from that corner:
+// the king can't be driven out, so it is a draw whatever the material says
+fn wrong_bishop_draw(board: &Position, strong: Color) -> bool {
+    let weak = if strong == Color::White { Color::Black } else { Color::White };
+    let only = |color: Color, pieces: &[(Piece, u8)]| {
+        [Piece::Pawn, Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen].iter().all(|&p| {
+            board.piece_count(color, p) == pieces.iter().find(|&&(q, _)| q == p).map_or(0, |&(_, n)| n)
+        })
+    };
+    let pawns = board.piece_count(strong, Piece::Pawn);
+    if pawns == 0 || !only(strong, &[(Piece::Pawn, pawns), (Piece::Bishop, 1)]) || !only(weak, &[]) {
+        return false;
+    }
+
+    let mut pawn_files = Vec::new();
+    let mut bishop_sq = 0;
+    for sq in 0u8..64 {
+        match board.squares[sq as usize] {
+            Some(cp) if cp.color == strong && cp.piece == Piece::Pawn => pawn_files.push(file_of(sq)),
+            Some(cp) if cp.color == strong && cp.piece == Piece::Bishop => bishop_sq = sq,
+            _ => {}
         }
     }
-    n
+    let file = pawn_files[0];
+    if (file != 0 && file != 7) || pawn_files.iter().any(|&f| f != file) { return false; }
+
+    let corner = make_sq(file, if strong == Color::White { 7 } else { 0 });
+    let light = |sq: u8| (file_of(sq) + rank_of(sq)) % 2 == 1;
+    if light(bishop_sq) == light(corner) { return false; }
+
+    board.find_king(weak).is_some_and(|k| {
+        (file_of(k) - file_of(corner)).abs() <= 1 && (rank_of(k) - rank_of(corner)).abs() <= 1
+    })
 }
 
 // ── Main entry ───────────────────────────────────────────────────────────────
 
-pub fn evaluate(board: &Board) -> i32 {
+/// Score from the side to move's perspective (what negamax wants)
+pub fn evaluate(board: &Position) -> i32 {
+    let score = evaluate_white(board);
+    (if board.side == Color::White { score } else { -score }) + TEMPO
+}
+
+/// Score from White's perspective, regardless of who is to move
+pub fn evaluate_white(board: &Position) -> i32 {
+    eval_features(board).dot(&EvalWeights::default())
+}
+
+// ── Features and weights, for tuning ─────────────────────────────────────────
+
+/// The evaluation's terms before weighting, each White minus Black. Terms
+/// that blend opening and endgame values come already blended by phase.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct EvalFeatures {
+    pub material:       [i32; 5], // piece count difference: pawn, knight, bishop, rook, queen
+    pub pst:            i32,
+    pub pawn_structure: i32,
+    pub king_safety:    i32,
+    pub bishop_pair:    i32,
+    pub queen_sortie:   i32,
+    pub rook_files:     i32,
+    pub seventh_rank:   i32,
+    pub king_tropism:   i32,
+    pub mobility:       i32, // squares attacked
+    pub threats:        i32,
+    pub passed_pawns:   i32,
+    pub drawn:          bool, // recognised fortress: scores 0 whatever the weights
+}
+
+/// One weight per feature; the defaults give `evaluate_white`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct EvalWeights {
+    pub material:       [i32; 5],
+    pub pst:            i32,
+    pub pawn_structure: i32,
+    pub king_safety:    i32,
+    pub bishop_pair:    i32,
+    pub queen_sortie:   i32,
+    pub rook_files:     i32,
+    pub seventh_rank:   i32,
+    pub king_tropism:   i32,
+    pub mobility:       i32,
+    pub threats:        i32,
+    pub passed_pawns:   i32,
+}
+
+impl Default for EvalWeights {
+    fn default() -> Self {
+        EvalWeights {
+            material:       [VAL_PAWN, VAL_KNIGHT, VAL_BISHOP, VAL_ROOK, VAL_QUEEN],
+            pst:            1,
+            pawn_structure: 1,
+            king_safety:    1,
+            bishop_pair:    1,
+            queen_sortie:   1,
+            rook_files:     1,
+            seventh_rank:   1,
+            king_tropism:   1,
+            mobility:       3,
+            threats:        1,
+            passed_pawns:   1,
+        }
+    }
+}
+
+impl EvalFeatures {
+    /// White-relative score under `w`
+    pub fn dot(&self, w: &EvalWeights) -> i32 {
+        if self.drawn { return 0; }
+        let material: i32 = self.material.iter().zip(w.material).map(|(f, w)| f * w).sum();
+        material
+            + self.pst * w.pst
+            + self.pawn_structure * w.pawn_structure
+            + self.king_safety * w.king_safety
+            + self.bishop_pair * w.bishop_pair
+            + self.queen_sortie * w.queen_sortie
+            + self.rook_files * w.rook_files
+            + self.seventh_rank * w.seventh_rank
+            + self.king_tropism * w.king_tropism
+            + self.mobility * w.mobility
+            + self.threats * w.threats
+            + self.passed_pawns * w.passed_pawns
+    }
+}
+
+pub fn eval_features(board: &Position) -> EvalFeatures {
+    if wrong_bishop_draw(board, Color::White) || wrong_bishop_draw(board, Color::Black) {
+        return EvalFeatures { drawn: true, ..EvalFeatures::default() };
+    }
+
     let phase = game_phase(board);
-    let mut score = 0i32;
+    let (white, black) = (attacks(board, Color::White), attacks(board, Color::Black));
+    let mut f = EvalFeatures::default();
 
     for sq in 0u8..64 {
         let Some(cp) = board.squares[sq as usize] else { continue };
-        let (op, eg, mat) = match cp.piece {
-            Piece::Pawn   => (&PAWN_OP,   &PAWN_EG,   VAL_PAWN),
-            Piece::Knight => (&KNIGHT_OP, &KNIGHT_EG, VAL_KNIGHT),
-            Piece::Bishop => (&BISHOP_OP, &BISHOP_EG, VAL_BISHOP),
-            Piece::Rook   => (&ROOK_OP,   &ROOK_EG,   VAL_ROOK),
-            Piece::Queen  => (&QUEEN_OP,  &QUEEN_EG,  VAL_QUEEN),
-            Piece::King   => (&KING_OP,   &KING_EG,   0),
+        let (op, eg) = match cp.piece {
+            Piece::Pawn   => (&PAWN_OP,   &PAWN_EG),
+            Piece::Knight => (&KNIGHT_OP, &KNIGHT_EG),
+            Piece::Bishop => (&BISHOP_OP, &BISHOP_EG),
+            Piece::Rook   => (&ROOK_OP,   &ROOK_EG),
+            Piece::Queen  => (&QUEEN_OP,  &QUEEN_EG),
+            Piece::King   => (&KING_OP,   &KING_EG),
         };
-        let val = mat + pst_blend(sq, cp.color, op, eg, phase);
-        if cp.color == Color::White { score += val; } else { score -= val; }
+        let sign = if cp.color == Color::White { 1 } else { -1 };
+        if cp.piece != Piece::King { f.material[cp.piece as usize] += sign; }
+        f.pst += sign * pst_blend(sq, cp.color, op, eg, phase);
     }
 
-    score += pawn_structure(board, Color::White) - pawn_structure(board, Color::Black);
-    score += king_safety(board, Color::White, phase) - king_safety(board, Color::Black, phase);
-    score += bishop_pair(board, Color::White) - bishop_pair(board, Color::Black);
-    score += rook_bonus(board, Color::White)  - rook_bonus(board, Color::Black);
-    score += (mobility(board, Color::White)   - mobility(board, Color::Black)) * 3;
-
-    if board.side == Color::White { score } else { -score }
+    f.pawn_structure = pawn_structure(board, Color::White) - pawn_structure(board, Color::Black);
+    f.king_safety    = king_safety(board, Color::White, phase, &black) - king_safety(board, Color::Black, phase, &white);
+    f.bishop_pair    = bishop_pair(board, Color::White) - bishop_pair(board, Color::Black);
+    f.queen_sortie   = queen_sortie(board, Color::White, phase) - queen_sortie(board, Color::Black, phase);
+    f.rook_files     = rook_bonus(board, Color::White)  - rook_bonus(board, Color::Black);
+    f.seventh_rank   = seventh_rank(board, Color::White) - seventh_rank(board, Color::Black);
+    f.king_tropism   = king_tropism(board, Color::White, phase) - king_tropism(board, Color::Black, phase);
+    f.mobility       = white.mobility - black.mobility;
+    f.threats        = threats(board, Color::White, &white, &black) - threats(board, Color::Black, &black, &white);
+    f.passed_pawns   = passed_pawns(board, Color::White, phase) - passed_pawns(board, Color::Black, phase);
+    f
 }
\ No newline at end of file
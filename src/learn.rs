@@ -0,0 +1,161 @@
+// learn.rs — Game outcomes by position, kept across games in a learning file
+//
+// At the end of a game every position reached after one of the engine's own
+// moves is credited with the result. Later searches nudge a root move by the
+// average result of the position it leads to, so a line that lost drifts out
+// of favour without being ruled out.
+
+use std::collections::HashMap;
+use std::io;
+
+use crate::board::{Board, Color, opposite};
+use crate::movegen::has_legal_move;
+
+pub const MAX_ENTRIES: usize = 50_000;
+
+const NUDGE_CP: i32 = 40;      // root score shift for an always-won (or lost) position
+const DECISIVE_CP: i32 = 400;  // unfinished game: a last score this big decides it
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Outcome { Win, Draw, Loss }
+
+#[derive(Clone, Copy, Default)]
+struct Record {
+    wins:   u32,
+    draws:  u32,
+    losses: u32,
+    tick:   u64, // when last recorded: the oldest go first once the table is full
+}
+
+#[derive(Default)]
+pub struct Learning {
+    entries: HashMap<u64, Record>,
+    path:    Option<String>,
+    tick:    u64,
+}
+
+impl Learning {
+    pub fn new() -> Self {
+        Learning::default()
+    }
+
+    /// Switches to the learning file at `path` and loads what it holds.
+    /// An empty path (or "<empty>") turns learning off.
+    pub fn set_file(&mut self, path: &str) -> io::Result<()> {
+        self.entries.clear();
+        self.tick = 0;
+        self.path = None;
+        if path.is_empty() || path == "<empty>" { return Ok(()); }
+        self.path = Some(path.to_string());
+
+        let text = match std::fs::read_to_string(path) {
+            Ok(t) => t,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        // One "hash wins draws losses" line per position, oldest first
+        for line in text.lines() {
+            let f: Vec<&str> = line.split_whitespace().collect();
+            let Some(hash) = f.first().and_then(|h| u64::from_str_radix(h, 16).ok()) else { continue };
+            let n = |i: usize| f.get(i).and_then(|s| s.parse().ok()).unwrap_or(0);
+            self.tick += 1;
+            self.entries.insert(hash, Record { wins: n(1), draws: n(2), losses: n(3), tick: self.tick });
+        }
+        self.trim();
+        Ok(())
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.path.is_some()
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Credits `outcome` (the engine's view) to each position
+    pub fn record(&mut self, positions: &[u64], outcome: Outcome) {
+        self.tick += 1;
+        for &hash in positions {
+            let r = self.entries.entry(hash).or_default();
+            match outcome {
+                Outcome::Win  => r.wins += 1,
+                Outcome::Draw => r.draws += 1,
+                Outcome::Loss => r.losses += 1,
+            }
+            r.tick = self.tick;
+        }
+        self.trim();
+    }
+
+    /// Centipawns to add to a root move that leads to `hash`
+    pub fn nudge(&self, hash: u64) -> i32 {
+        let Some(r) = self.entries.get(&hash) else { return 0 };
+        let games = (r.wins + r.draws + r.losses).max(1) as i32;
+        NUDGE_CP * (r.wins as i32 - r.losses as i32) / games
+    }
+
+    /// Writes the table to the learning file, if one is set
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else { return Ok(()) };
+        let mut entries: Vec<(&u64, &Record)> = self.entries.iter().collect();
+        entries.sort_by_key(|(_, r)| r.tick);
+        let text: String = entries.iter()
+            .map(|(h, r)| format!("{:016x} {} {} {}\n", h, r.wins, r.draws, r.losses))
+            .collect();
+        std::fs::write(path, text)
+    }
+
+    // Over the cap: drop the least recently recorded quarter in one go
+    fn trim(&mut self) {
+        if self.entries.len() <= MAX_ENTRIES { return; }
+        let mut ticks: Vec<u64> = self.entries.values().map(|r| r.tick).collect();
+        ticks.sort_unstable();
+        let cutoff = ticks[ticks.len() - MAX_ENTRIES * 3 / 4];
+        self.entries.retain(|_, r| r.tick >= cutoff);
+        // Ties at the cutoff can leave a few too many; drop them too
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.retain(|_, r| r.tick > cutoff);
+        }
+    }
+}
+
+/// Positions of the game on `board` with `engine` having just moved (the
+/// game's initial position, which no move led to, is left out)
+pub fn engine_positions(board: &Board, engine: Color) -> Vec<u64> {
+    let hashes = board.position_hashes.iter().chain(std::iter::once(&board.hash));
+    let total = board.position_hashes.len();
+    hashes.enumerate()
+        .skip(1)
+        .filter(|&(i, _)| {
+            // Sides alternate back from the current position
+            let to_move = if (total - i).is_multiple_of(2) { board.side } else { opposite(board.side) };
+            to_move != engine
+        })
+        .map(|(_, &h)| h)
+        .collect()
+}
+
+/// How the game on `board` went for `engine`: decided on the board if it is
+/// over, otherwise by the engine's last search score if that is decisive
+pub fn game_outcome(board: &Board, engine: Color, last_score: i32) -> Option<Outcome> {
+    if !has_legal_move(board) {
+        if !board.in_check() { return Some(Outcome::Draw); }
+        return Some(if board.side == engine { Outcome::Loss } else { Outcome::Win });
+    }
+    if board.is_fifty_move_rule() || board.is_repetition() {
+        return Some(Outcome::Draw);
+    }
+    // The score is from the engine's side at its last search
+    if last_score >= DECISIVE_CP { Some(Outcome::Win) }
+    else if last_score <= -DECISIVE_CP { Some(Outcome::Loss) }
+    else { None }
+}
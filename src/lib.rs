@@ -0,0 +1,16 @@
+// lib.rs — AbhinEngine as a library (board, move generation, eval, search)
+
+// Bounds are written out as `x >= 0 && x < 8` throughout; keep that style.
+#![allow(clippy::manual_range_contains)]
+
+pub mod board;
+pub mod search;
+pub mod eval;
+pub mod movegen;
+pub mod book;
+pub mod zobrist;
+pub mod time;
+pub mod epd;
+pub mod learn;
+#[cfg(feature = "syzygy")]
+pub mod tablebase;
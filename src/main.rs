@@ -1,20 +1,33 @@
 // main.rs — UCI interface for AbhinEngine with proper time management
 
-use std::io::{self, BufRead};
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
 
-mod board;
-mod search;
-mod eval;
-mod movegen;
-mod book;
-
-use board::Board;
-use search::SearchEngine;
+use chess_engine::board::{self, Board};
+use chess_engine::epd::{epd_moves, parse_epd};
+use chess_engine::learn::{engine_positions, game_outcome};
+use chess_engine::movegen::{perft_divide, perft_hash};
+use chess_engine::search::{SearchEngine, MAX_MULTI_PV, MAX_SKILL};
+use chess_engine::time::{get_val, pick_time, DEFAULT_MOVE_OVERHEAD};
 
 fn main() {
     let stdin = io::stdin();
-    let mut engine = SearchEngine::new();
+    // The search runs on its own thread holding the engine lock, so the
+    // loop stays free to answer "isready", "stop" and "debug"; any command
+    // that needs the engine simply waits for the lock.
+    let engine = Arc::new(Mutex::new(SearchEngine::new()));
+    let stop = engine.lock().unwrap().stop_signal.clone();
+    let ponder = engine.lock().unwrap().ponder.clone();
+    let debug = engine.lock().unwrap().debug.clone();
+    let mut search: Option<JoinHandle<()>> = None;
     let mut board = Board::start_pos();
+    let mut move_overhead = DEFAULT_MOVE_OVERHEAD;
+    let mut min_think = 0;
+    let mut engine_side = None; // the side the engine last searched for, this game
+    let mut hash_mb = 64;
+    let mut config_shown = false; // options summary goes out with the first isready
 
     for line in stdin.lock().lines() {
         let line = match line { Ok(l) => l, Err(_) => break };
@@ -25,112 +38,394 @@ fn main() {
                 println!("id name AbhinEngine 1.0.1");
                 println!("id author Abhin");
                 println!("option name Hash type spin default 64 min 1 max 512");
+                println!("option name Clear Hash type button");
                 println!("option name Ponder type check default false");
+                println!("option name OwnBook type check default true");
+                println!("option name BookSeed type spin default 0 min 0 max 2147483647");
+                println!("option name Deterministic type check default false");
+                println!("option name Contempt type spin default 10 min -100 max 100");
+                println!("option name MultiPV type spin default 1 min 1 max {}", MAX_MULTI_PV);
+                println!("option name UCI_ShowWDL type check default false");
+                println!("option name Skill Level type spin default {} min 0 max {}", MAX_SKILL, MAX_SKILL);
+                println!("option name Variety type spin default 0 min 0 max 100");
+                println!("option name VarietyMoves type spin default 10 min 0 max 100");
+                println!("option name LearningFile type string default <empty>");
+                #[cfg(feature = "syzygy")]
+                println!("option name SyzygyPath type string default <empty>");
+                println!("option name MoveOverhead type spin default {} min 0 max 5000",
+                    DEFAULT_MOVE_OVERHEAD);
+                println!("option name Minimum Thinking Time type spin default 0 min 0 max 5000");
                 println!("uciok");
             }
-            "isready"    => println!("readyok"),
+            "isready" => {
+                // A search holding the engine must not delay readyok; the
+                // summary then waits for a later isready
+                if !config_shown {
+                    if let Ok(eng) = engine.try_lock() {
+                        print_options(&eng, hash_mb, move_overhead, min_think);
+                        config_shown = true;
+                    }
+                }
+                println!("readyok");
+            }
+            "options" => {
+                // Like isready, never wait on a running search; one already
+                // told to stop lets go of the engine at once
+                let eng = match engine.try_lock() {
+                    Ok(eng) => Some(eng),
+                    Err(_) if stop.load(Ordering::Relaxed) => Some(engine.lock().unwrap()),
+                    Err(_) => None,
+                };
+                match eng {
+                    Some(eng) => print_options(&eng, hash_mb, move_overhead, min_think),
+                    None => println!("info string options unavailable while searching"),
+                }
+            }
+            "stop"       => stop.store(true, Ordering::Relaxed),
+            // The pondered move was played: same search, now on the clock
+            "ponderhit"  => ponder.store(false, Ordering::Relaxed),
+            "debug on"   => debug.store(true, Ordering::Relaxed),
+            "debug off"  => debug.store(false, Ordering::Relaxed),
+            // No registration needed — acknowledge and carry on
+            _ if line.starts_with("register") => println!("registration ok"),
             _ if line.starts_with("setoption name Hash value") => {
                 let parts: Vec<&str> = line.split_whitespace().collect();
                 if let Some(mb) = parts.last().and_then(|s| s.parse::<usize>().ok()) {
-                    engine.tt.resize(mb);
+                    hash_mb = mb;
+                    engine.lock().unwrap().tt.resize(mb);
+                }
+            }
+            _ if line.starts_with("setoption name Clear Hash") => engine.lock().unwrap().tt.clear(),
+            _ if line.starts_with("setoption name OwnBook value") => {
+                engine.lock().unwrap().book.enabled = line.ends_with("true");
+            }
+            _ if line.starts_with("setoption name BookSeed value") => {
+                let parts: Vec<&str> = line.split_whitespace().collect();
+                if let Some(seed) = parts.last().and_then(|s| s.parse::<u64>().ok()) {
+                    engine.lock().unwrap().book.set_seed(seed);
+                }
+            }
+            _ if line.starts_with("setoption name Deterministic value") => {
+                engine.lock().unwrap().deterministic = line.ends_with("true");
+            }
+            _ if line.starts_with("setoption name Skill Level value") => {
+                let parts: Vec<&str> = line.split_whitespace().collect();
+                if let Some(level) = parts.last().and_then(|s| s.parse::<u8>().ok()) {
+                    engine.lock().unwrap().skill_level = level.min(MAX_SKILL);
+                }
+            }
+            _ if line.starts_with("setoption name Variety value") => {
+                let parts: Vec<&str> = line.split_whitespace().collect();
+                if let Some(cp) = parts.last().and_then(|s| s.parse::<i32>().ok()) {
+                    engine.lock().unwrap().variety = cp.clamp(0, 100);
+                }
+            }
+            _ if line.starts_with("setoption name VarietyMoves value") => {
+                let parts: Vec<&str> = line.split_whitespace().collect();
+                if let Some(n) = parts.last().and_then(|s| s.parse::<u32>().ok()) {
+                    engine.lock().unwrap().variety_moves = n.min(100);
+                }
+            }
+            _ if line.starts_with("setoption name LearningFile value") => {
+                let path = line["setoption name LearningFile value".len()..].trim();
+                if let Err(e) = engine.lock().unwrap().learning.set_file(path) {
+                    println!("info string cannot read {}: {}", path, e);
+                }
+            }
+            // The binary has no table reader: only a probe a library user
+            // plugs into SearchEngine::tablebase reads them. The option just
+            // checks what is there, and says the tables go unused
+            #[cfg(feature = "syzygy")]
+            _ if line.starts_with("setoption name SyzygyPath value") => {
+                let path = line["setoption name SyzygyPath value".len()..].trim();
+                if !path.is_empty() && path != "<empty>" {
+                    match chess_engine::tablebase::scan_syzygy_dir(path) {
+                        Ok((tables, pieces)) => println!(
+                            "info string SyzygyPath {}: {} WDL tables, up to {} pieces; \
+                            no tablebase reader is installed, so they are not used", path, tables, pieces),
+                        Err(e) => println!("info string cannot read {}: {}", path, e),
+                    }
+                }
+            }
+            _ if line.starts_with("setoption name MultiPV value") => {
+                let parts: Vec<&str> = line.split_whitespace().collect();
+                if let Some(n) = parts.last().and_then(|s| s.parse::<usize>().ok()) {
+                    engine.lock().unwrap().multi_pv = n.clamp(1, MAX_MULTI_PV);
+                }
+            }
+            _ if line.starts_with("setoption name UCI_ShowWDL value") => {
+                engine.lock().unwrap().show_wdl = line.ends_with("true");
+            }
+            _ if line.starts_with("setoption name Contempt value") => {
+                let parts: Vec<&str> = line.split_whitespace().collect();
+                if let Some(cp) = parts.last().and_then(|s| s.parse::<i32>().ok()) {
+                    engine.lock().unwrap().contempt = cp.clamp(-100, 100);
+                }
+            }
+            _ if line.starts_with("setoption name MoveOverhead value") => {
+                let parts: Vec<&str> = line.split_whitespace().collect();
+                if let Some(ms) = parts.last().and_then(|s| s.parse::<u64>().ok()) {
+                    move_overhead = ms.min(5000);
+                }
+            }
+            _ if line.starts_with("setoption name Minimum Thinking Time value") => {
+                let parts: Vec<&str> = line.split_whitespace().collect();
+                if let Some(ms) = parts.last().and_then(|s| s.parse::<u64>().ok()) {
+                    min_think = ms.min(5000);
+                }
+            }
+            // Hidden options: switch individual pruning heuristics off
+            _ if line.starts_with("setoption name NullMove value") => {
+                engine.lock().unwrap().use_nullmove = line.ends_with("true");
+            }
+            _ if line.starts_with("setoption name LMR value") => {
+                engine.lock().unwrap().use_lmr = line.ends_with("true");
+            }
+            _ if line.starts_with("setoption name Futility value") => {
+                engine.lock().unwrap().use_futility = line.ends_with("true");
+            }
+            _ if line.starts_with("setoption name AspirationWindow value") => {
+                let parts: Vec<&str> = line.split_whitespace().collect();
+                if let Some(cp) = parts.last().and_then(|s| s.parse::<i32>().ok()) {
+                    engine.lock().unwrap().aspiration_window = cp.clamp(0, 1000);
+                }
+            }
+            _ if line.starts_with("setoption name InfoInterval value") => {
+                let parts: Vec<&str> = line.split_whitespace().collect();
+                if let Some(ms) = parts.last().and_then(|s| s.parse::<u64>().ok()) {
+                    engine.lock().unwrap().info_interval_ms = ms;
+                }
+            }
+            _ if line.starts_with("setoption name AspirationGrowth value") => {
+                let parts: Vec<&str> = line.split_whitespace().collect();
+                if let Some(g) = parts.last().and_then(|s| s.parse::<i32>().ok()) {
+                    engine.lock().unwrap().aspiration_growth = g.clamp(2, 16);
                 }
             }
             "ucinewgame" => {
+                stop_search(&mut search, &stop, &ponder);
+                let mut eng = engine.lock().unwrap();
+                finish_game(&mut eng, &board, engine_side.take());
+                eng.clear();
                 board = Board::start_pos();
-                engine.clear();
             }
             "quit" => break,
             _ if line.starts_with("position") => {
-                board = parse_position(line);
-                engine.push_position(&board);
+                // The board carries the game's moves; the search counts
+                // repetitions against them
+                let next = parse_position(line);
+                // No ucinewgame between games: a position unrelated to the
+                // last one starts a new game all the same. It may as well be
+                // a takeback, so nothing is learned from the old one.
+                if !next.continues(&board) {
+                    stop_search(&mut search, &stop, &ponder);
+                    engine_side = None;
+                    let mut eng = engine.lock().unwrap();
+                    eng.clear();
+                    if debug.load(Ordering::Relaxed) { println!("info string debug new game, search state cleared"); }
+                } else if engine_side.is_some() && next.is_game_over() {
+                    // Over on the board: the result is known now
+                    stop_search(&mut search, &stop, &ponder);
+                    finish_game(&mut engine.lock().unwrap(), &next, engine_side.take());
+                }
+                board = next;
             }
             _ if line.starts_with("go") => {
-                let (max_depth, time_ms) = pick_time(line, &board);
-                engine.tt.clear();
-                let (best_move, _score) = engine.search(&mut board, max_depth, time_ms);
-                println!("bestmove {}", best_move.to_uci());
-            }
-            _ => {}
-        }
-    }
-}
+                // A previous search must have ended before a new one starts
+                if let Some(handle) = search.take() { let _ = handle.join(); }
+                stop.store(false, Ordering::Relaxed);
+                let pondering = line.split_whitespace().any(|t| t == "ponder");
+                ponder.store(pondering, Ordering::Relaxed);
 
-/// Returns (max_depth, time_limit_ms)
-fn pick_time(line: &str, board: &Board) -> (u8, u64) {
-    let parts: Vec<&str> = line.split_whitespace().collect();
+                let mut eng = engine.lock().unwrap();
+                // The book picks at random, so deterministic runs skip it; a
+                // ponder search must not answer before ponderhit
+                let book_move = if eng.deterministic || pondering { None } else { eng.book.probe(&board) };
+                if let Some(mv) = book_move {
+                    println!("bestmove {}", mv.to_uci());
+                    continue;
+                }
+                engine_side = Some(board.side);
+                let (max_depth, time_ms) = pick_time(line, &board, move_overhead, min_think);
+                eng.search_moves = parse_searchmoves(line, &board);
+                let parts: Vec<&str> = line.split_whitespace().collect();
+                eng.node_limit = get_val(&parts, "nodes").unwrap_or(0);
+                drop(eng);
 
-    // Explicit depth — give plenty of time
-    for i in 0..parts.len() {
-        if parts[i] == "depth" {
-            if let Some(d) = parts.get(i+1).and_then(|s| s.parse::<u8>().ok()) {
-                return (d.min(12), 300_000);
+                let engine = Arc::clone(&engine);
+                let mut board = board.clone();
+                search = Some(thread::spawn(move || {
+                    // The TT carries over between moves; search() ages it
+                    let mut engine = engine.lock().unwrap();
+                    let (best_move, _score) = engine.search(&mut board, max_depth, time_ms);
+                    println!("bestmove {}", best_move.to_uci());
+                }));
+            }
+            // testsuite <path> [movetime]: run an EPD suite, tally bm/am hits
+            _ if line.starts_with("testsuite") => {
+                if let Some(handle) = search.take() { let _ = handle.join(); }
+                let parts: Vec<&str> = line.split_whitespace().collect();
+                match parts.get(1) {
+                    Some(path) => {
+                        let movetime = parts.get(2).and_then(|s| s.parse().ok()).unwrap_or(1000);
+                        run_testsuite(&mut engine.lock().unwrap(), path, movetime);
+                    }
+                    None => println!("info string usage: testsuite <path> [movetime]"),
+                }
+            }
+            // Debug: mirror the position (colors swapped) to check eval symmetry
+            "flip" => {
+                board = board.mirror();
+                println!("info string eval {}", chess_engine::eval::evaluate(&board));
+            }
+            // Debug: perft that checks the incremental hash at every leaf
+            _ if line.starts_with("perfthash") => {
+                let depth = line.split_whitespace().nth(1)
+                    .and_then(|s| s.parse::<u8>().ok()).unwrap_or(1);
+                if let Some(handle) = search.take() { let _ = handle.join(); }
+                let (nodes, bad) = perft_hash(&mut board, depth);
+                println!("Nodes searched: {}", nodes);
+                println!("Hash mismatches: {}", bad);
             }
+            _ if line.starts_with("perft") => {
+                let depth = line.split_whitespace().nth(1)
+                    .and_then(|s| s.parse::<u8>().ok()).unwrap_or(1);
+                if let Some(handle) = search.take() { let _ = handle.join(); }
+                let mut total = 0;
+                for (mv, n) in perft_divide(&mut board, depth) {
+                    println!("{}: {}", mv.to_uci(), n);
+                    total += n;
+                }
+                println!();
+                println!("Nodes searched: {}", total);
+            }
+            _ => {}
         }
     }
 
-    // Infinite — search deep with lots of time
-    if line.contains("infinite") {
-        return (12, 300_000);
-    }
+    // Quit or end of input: nothing is cut off mid-line on exit
+    stop_search(&mut search, &stop, &ponder);
+    let _ = io::stdout().flush();
+}
 
-    // Movetime — use exactly that much time
-    if let Some(mt) = get_val(&parts, "movetime") {
-        return (12, mt.saturating_sub(50).max(50));
-    }
+/// Ends a running search (pondering included) and waits for its bestmove.
+/// Commands that replace the game call this before taking the engine
+/// lock, which the search holds until it finishes.
+fn stop_search(search: &mut Option<JoinHandle<()>>, stop: &AtomicBool, ponder: &AtomicBool) {
+    let Some(handle) = search.take() else { return };
+    stop.store(true, Ordering::Relaxed);
+    ponder.store(false, Ordering::Relaxed);
+    if handle.join().is_err() { eprintln!("search thread panicked"); }
+}
 
-    // Clock-based time management
-    let time_key = if board.side == board::Color::White { "wtime" } else { "btime" };
-    let inc_key  = if board.side == board::Color::White { "winc"  } else { "binc"  };
-    let movestogo_key = "movestogo";
+/// One info string with the current option values, for logs of user setups
+fn print_options(engine: &SearchEngine, hash_mb: usize, move_overhead: u64, min_think: u64) {
+    println!("info string options Hash {}, Threads 1, OwnBook {}, BookSeed {}, Contempt {}, \
+        MultiPV {}, Skill Level {}, Variety {}, VarietyMoves {}, UCI_ShowWDL {}, Deterministic {}, \
+        MoveOverhead {}, Minimum Thinking Time {}, LearningFile {}",
+        hash_mb, engine.book.enabled, engine.book.seed(), engine.contempt, engine.multi_pv,
+        engine.skill_level, engine.variety, engine.variety_moves, engine.show_wdl,
+        engine.deterministic, move_overhead, min_think, engine.learning.path().unwrap_or("<empty>"));
+}
 
-    let clock_ms = get_val(&parts, time_key).unwrap_or(10_000);
-    let inc_ms   = get_val(&parts, inc_key).unwrap_or(0);
-    let movestogo = get_val(&parts, movestogo_key).unwrap_or(25);
+/// Credits the finished game to the learning table and saves it. Called
+/// when the game ends on the board or at ucinewgame; a game with no clear
+/// result (abandoned mid-way, scores level) is not recorded.
+fn finish_game(engine: &mut SearchEngine, board: &Board, side: Option<board::Color>) {
+    let Some(side) = side else { return };
+    if !engine.learning.enabled() { return; }
+    // The last search was for `side`, so its score is already the engine's view
+    let Some(outcome) = game_outcome(board, side, engine.last_score()) else { return };
+    engine.learning.record(&engine_positions(board, side), outcome);
+    if let Err(e) = engine.learning.save() {
+        println!("info string cannot save learning file: {}", e);
+    }
+}
 
-    // How much time to spend this move:
-    // Use clock/movestogo + a fraction of increment
-    let alloc = (clock_ms / movestogo.max(1)) + inc_ms * 3 / 4;
+/// Moves listed after "searchmoves", resolved to legal moves (illegal ones dropped)
+fn parse_searchmoves(line: &str, board: &Board) -> Vec<board::Move> {
+    const GO_KEYWORDS: [&str; 11] = ["ponder", "wtime", "btime", "winc", "binc",
+        "movestogo", "depth", "nodes", "mate", "movetime", "infinite"];
+    line.split_whitespace()
+        .skip_while(|&p| p != "searchmoves")
+        .skip(1)
+        .take_while(|p| !GO_KEYWORDS.contains(p))
+        .filter_map(|uci| board.parse_uci(uci))
+        .collect()
+}
 
-    // Never use more than 1/3 of remaining clock
-    let alloc = alloc.min(clock_ms / 3);
+/// Searches every EPD record for `movetime` ms; a position passes when the
+/// move is among its `bm` moves (if any) and not among its `am` moves.
+fn run_testsuite(engine: &mut SearchEngine, path: &str, movetime: u64) {
+    let text = match std::fs::read_to_string(path) {
+        Ok(t) => t,
+        Err(e) => { println!("info string cannot read {}: {}", path, e); return; }
+    };
 
-    // Safety margin
-    let alloc = alloc.saturating_sub(50).max(50);
+    let (mut passed, mut total) = (0, 0);
+    for line in text.lines().filter(|l| !l.trim().is_empty()) {
+        let (mut board, ops) = parse_epd(line);
+        let bm = epd_moves(&board, &ops, "bm");
+        let am = epd_moves(&board, &ops, "am");
+        total += 1;
+        let id = ops.get("id").cloned().unwrap_or_else(|| format!("#{}", total));
 
-    (12, alloc)
-}
+        engine.clear();
+        engine.search_moves.clear();
+        engine.node_limit = 0;
+        let (mv, _) = engine.search(&mut board, 12, movetime);
+        let ok = (bm.is_empty() || bm.contains(&mv)) && !am.contains(&mv);
+        if ok { passed += 1; }
 
-fn get_val(parts: &[&str], key: &str) -> Option<u64> {
-    parts.iter().position(|&p| p == key)
-        .and_then(|i| parts.get(i+1))
-        .and_then(|s| s.parse().ok())
+        let expected = ops.get("bm").map(|m| format!("bm {}", m))
+            .or_else(|| ops.get("am").map(|m| format!("am {}", m)))
+            .unwrap_or_default();
+        println!("{}: {} {} ({})", id, if ok { "pass" } else { "fail" }, board.to_san(mv), expected);
+    }
+    println!("testsuite {}/{} passed", passed, total);
 }
 
+/// Reads "position [startpos | fen <fen>] [moves ...]". Malformed input
+/// (no base position, a FEN missing either king, an illegal move) is
+/// reported with an info string: the base falls back to the start position,
+/// and the move list stops at the first move that can't be played.
 fn parse_position(line: &str) -> Board {
-    let mut board = Board::start_pos();
     let parts: Vec<&str> = line.split_whitespace().collect();
     let mut i = 1;
 
-    if i < parts.len() {
-        if parts[i] == "startpos" {
-            board = Board::start_pos();
+    let mut board = match parts.get(i).copied() {
+        Some("startpos") => {
             i += 1;
-        } else if parts[i] == "fen" {
+            Board::start_pos()
+        }
+        Some("fen") => {
             i += 1;
             let fen_end = parts[i..].iter()
                 .position(|&p| p == "moves")
                 .unwrap_or(parts.len() - i);
             let fen = parts[i..i+fen_end].join(" ");
-            board = Board::from_fen(&fen);
             i += fen_end;
+            let board = Board::from_fen(&fen);
+            if board.piece_count(board::Color::White, board::Piece::King) != 1
+                || board.piece_count(board::Color::Black, board::Piece::King) != 1
+            {
+                println!("info string invalid fen \"{}\", using the start position", fen);
+                return Board::start_pos();
+            }
+            board
         }
-    }
+        _ => {
+            println!("info string position without startpos or fen, using the start position");
+            Board::start_pos()
+        }
+    };
 
-    if i < parts.len() && parts[i] == "moves" {
-        i += 1;
-        while i < parts.len() {
-            board.make_uci_move(parts[i]);
-            i += 1;
+    if parts.get(i) == Some(&"moves") {
+        for uci in &parts[i + 1..] {
+            if !board.make_uci_move(uci) {
+                println!("info string illegal move {}, ignoring the rest", uci);
+                break;
+            }
         }
     }
 
@@ -1,47 +1,220 @@
 // movegen.rs — Legal move generation
 
-use crate::board::{Board, Color, Move, Piece, ColoredPiece, opposite};
-
-pub fn generate_moves(board: &Board) -> Vec<Move> {
-    let mut moves = generate_pseudo_legal(board);
-    // Filter out moves that leave king in check
-    moves.retain(|&mv| {
-        let mut b = board.clone();
-        b.make_move(mv);
-        let king_sq = b.find_king(board.side);
-        let legal = king_sq.map(|sq| !b.is_attacked(sq, opposite(board.side))).unwrap_or(false);
-        legal
-    });
+use crate::board::{Board, Color, ColoredPiece, Move, Piece, Position, file_of, make_sq, opposite, rank_of};
+
+// ── MoveList ─────────────────────────────────────────────────────────────────
+
+pub const MAX_MOVES: usize = 256; // no legal position has more than 218
+
+/// Fixed-capacity, stack-allocated move buffer used by the search so move
+/// generation doesn't hit the allocator at every node.
+#[derive(Clone)]
+pub struct MoveList {
+    moves: [Move; MAX_MOVES],
+    len:   usize,
+}
+
+impl MoveList {
+    pub fn new() -> Self {
+        MoveList { moves: [Move::null(); MAX_MOVES], len: 0 }
+    }
+
+    pub fn push(&mut self, mv: Move) {
+        if self.len < MAX_MOVES {
+            self.moves[self.len] = mv;
+            self.len += 1;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Keeps only the moves matching `keep`, preserving order
+    pub fn retain(&mut self, mut keep: impl FnMut(&Move) -> bool) {
+        let mut n = 0;
+        for i in 0..self.len {
+            if keep(&self.moves[i]) {
+                self.moves[n] = self.moves[i];
+                n += 1;
+            }
+        }
+        self.len = n;
+    }
+}
+
+impl Default for MoveList {
+    fn default() -> Self { Self::new() }
+}
+
+impl std::ops::Deref for MoveList {
+    type Target = [Move];
+    fn deref(&self) -> &[Move] { &self.moves[..self.len] }
+}
+
+impl std::ops::DerefMut for MoveList {
+    fn deref_mut(&mut self) -> &mut [Move] { &mut self.moves[..self.len] }
+}
+
+// ── Generation entry points ──────────────────────────────────────────────────
+
+pub fn generate_moves(board: &Position) -> Vec<Move> {
+    let mut list = MoveList::new();
+    generate_moves_into(board, &mut list);
+    list.to_vec()
+}
+
+/// Legal moves in a canonical order: by from square, then to square, then
+/// promotion piece (none first). Independent of generation order, for tools
+/// and tests that compare move lists.
+pub fn generate_moves_sorted(board: &Position) -> Vec<Move> {
+    let mut moves = generate_moves(board);
+    moves.sort_by_key(|m| (m.from, m.to, m.promotion.map_or(0, |p| p as u8 + 1)));
     moves
 }
 
-pub fn generate_captures(board: &Board) -> Vec<Move> {
+/// Legal moves, generated one from-square at a time as the iterator is
+/// consumed: stopping early skips the squares not yet reached, and nothing
+/// is allocated
+pub fn legal_moves(board: &Position) -> impl Iterator<Item = Move> + '_ {
+    LegalMoves { board, next_from: 0, square: SquareMoves { moves: [Move::null(); 32], len: 0 }, i: 0 }
+}
+
+struct LegalMoves<'a> {
+    board:     &'a Position,
+    next_from: u8,          // next square to generate from
+    square:    SquareMoves, // pseudo-legal moves of the last square generated
+    i:         usize,       // next of those to check
+}
+
+impl Iterator for LegalMoves<'_> {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Move> {
+        loop {
+            while self.i < self.square.len {
+                let mv = self.square.moves[self.i];
+                self.i += 1;
+                if is_legal(self.board, mv) { return Some(mv); }
+            }
+            if self.next_from >= 64 { return None; }
+            self.square.len = 0;
+            self.i = 0;
+            gen_square(self.board, self.next_from, &mut self.square);
+            self.next_from += 1;
+        }
+    }
+}
+
+// A queen has at most 27 moves, a pawn 12 (promotions), a king 8 + 2
+struct SquareMoves {
+    moves: [Move; 32],
+    len:   usize,
+}
+
+/// Like `generate_moves`, but fills a caller-owned buffer
+pub fn generate_moves_into(board: &Position, list: &mut MoveList) {
+    list.clear();
+    gen_pseudo_into(board, list);
+    list.retain(|&mv| is_legal(board, mv));
+}
+
+pub fn generate_captures(board: &Position) -> Vec<Move> {
     generate_moves(board).into_iter().filter(|m| m.captured.is_some() || m.is_ep).collect()
 }
 
-fn generate_pseudo_legal(board: &Board) -> Vec<Move> {
-    let mut moves = Vec::with_capacity(50);
+pub fn generate_captures_into(board: &Position, list: &mut MoveList) {
+    generate_moves_into(board, list);
+    list.retain(|m| m.captured.is_some() || m.is_ep);
+}
+
+/// The quiescence move set: captures plus queen promotions, which swing
+/// material as much as a capture even when nothing is taken
+pub fn generate_tactical_into(board: &Position, list: &mut MoveList) {
+    generate_moves_into(board, list);
+    list.retain(|m| m.captured.is_some() || m.is_ep || m.promotion == Some(Piece::Queen));
+}
+
+// Filter out moves that leave king in check. The move is actually played,
+// so an en passant capture that removes both pawns from a rank pinned by a
+// rook or queen is rejected too — a pin-based shortcut would have to treat
+// that case specially.
+// Only the `Copy` position is played on; no history vectors are cloned per move.
+fn is_legal(board: &Position, mv: Move) -> bool {
+    let mut after = *board;
+    after.make_move(mv);
+    board.find_king(board.side).is_some() && !after.king_attacked(board.side)
+}
+
+/// Number of legal moves, without building a move list. Callers that only
+/// need "any legal move?" should use `has_legal_move`, which stops early.
+pub fn count_legal_moves(board: &Position) -> usize {
+    let mut counter = LegalCounter { board, limit: usize::MAX, count: 0 };
+    gen_pseudo_into(board, &mut counter);
+    counter.count
+}
+
+pub fn has_legal_move(board: &Position) -> bool {
+    let mut counter = LegalCounter { board, limit: 1, count: 0 };
+    gen_pseudo_into(board, &mut counter);
+    counter.count > 0
+}
+
+// Generators emit into a sink so counting doesn't have to collect moves.
+trait MoveSink {
+    fn push(&mut self, mv: Move);
+}
+
+impl MoveSink for SquareMoves {
+    fn push(&mut self, mv: Move) {
+        self.moves[self.len] = mv;
+        self.len += 1;
+    }
+}
+
+impl MoveSink for MoveList {
+    fn push(&mut self, mv: Move) { MoveList::push(self, mv); }
+}
+
+// Counts legal moves as they are generated, without collecting them
+struct LegalCounter<'a> {
+    board: &'a Position,
+    limit: usize,
+    count: usize,
+}
+
+impl MoveSink for LegalCounter<'_> {
+    fn push(&mut self, mv: Move) {
+        if self.count >= self.limit { return; }
+        if is_legal(self.board, mv) { self.count += 1; }
+    }
+}
 
+fn gen_pseudo_into<S: MoveSink>(board: &Position, moves: &mut S) {
     for from in 0u8..64 {
-        let Some(cp) = board.squares[from as usize] else { continue };
-        if cp.color != board.side { continue; }
-
-        match cp.piece {
-            Piece::Pawn   => gen_pawn_moves(board, from, cp.color, &mut moves),
-            Piece::Knight => gen_leaper_moves(board, from, cp.color, &KNIGHT_DELTAS, &mut moves),
-            Piece::Bishop => gen_slider_moves(board, from, cp.color, &BISHOP_DIRS, &mut moves),
-            Piece::Rook   => gen_slider_moves(board, from, cp.color, &ROOK_DIRS, &mut moves),
-            Piece::Queen  => {
-                gen_slider_moves(board, from, cp.color, &BISHOP_DIRS, &mut moves);
-                gen_slider_moves(board, from, cp.color, &ROOK_DIRS, &mut moves);
-            }
-            Piece::King   => {
-                gen_leaper_moves(board, from, cp.color, &KING_DELTAS, &mut moves);
-                gen_castling(board, from, cp.color, &mut moves);
-            }
+        gen_square(board, from, moves);
+    }
+}
+
+// Pseudo-legal moves of the side to move's piece on `from`, if any
+fn gen_square<S: MoveSink>(board: &Position, from: u8, moves: &mut S) {
+    let Some(cp) = board.squares[from as usize] else { return };
+    if cp.color != board.side { return; }
+
+    match cp.piece {
+        Piece::Pawn   => gen_pawn_moves(board, from, cp.color, moves),
+        Piece::Knight => gen_leaper_moves(board, from, cp.color, &KNIGHT_DELTAS, moves),
+        Piece::Bishop => gen_slider_moves(board, from, cp.color, &BISHOP_DIRS, moves),
+        Piece::Rook   => gen_slider_moves(board, from, cp.color, &ROOK_DIRS, moves),
+        Piece::Queen  => {
+            gen_slider_moves(board, from, cp.color, &BISHOP_DIRS, moves);
+            gen_slider_moves(board, from, cp.color, &ROOK_DIRS, moves);
+        }
+        Piece::King   => {
+            gen_leaper_moves(board, from, cp.color, &KING_DELTAS, moves);
+            gen_castling(board, from, cp.color, moves);
         }
     }
-    moves
 }
 
 const KNIGHT_DELTAS: [(i32,i32);8] = [(-2,-1),(-2,1),(-1,-2),(-1,2),(1,-2),(1,2),(2,-1),(2,1)];
@@ -49,18 +222,17 @@ const KING_DELTAS:   [(i32,i32);8] = [(-1,-1),(-1,0),(-1,1),(0,-1),(0,1),(1,-1),
 const BISHOP_DIRS:   [(i32,i32);4] = [(-1,-1),(-1,1),(1,-1),(1,1)];
 const ROOK_DIRS:     [(i32,i32);4] = [(-1,0),(1,0),(0,-1),(0,1)];
 
-fn gen_pawn_moves(board: &Board, from: u8, color: Color, moves: &mut Vec<Move>) {
+fn gen_pawn_moves<S: MoveSink>(board: &Position, from: u8, color: Color, moves: &mut S) {
     let dir: i32 = if color == Color::White { 1 } else { -1 };
     let start_rank = if color == Color::White { 1 } else { 6 };
     let promo_rank  = if color == Color::White { 7 } else { 0 };
 
-    let fr = (from / 8) as i32;
-    let ff = (from % 8) as i32;
+    let (fr, ff) = (rank_of(from), file_of(from));
 
     // Single push
     let tr = fr + dir;
     if tr >= 0 && tr < 8 {
-        let to = (tr * 8 + ff) as u8;
+        let to = make_sq(ff, tr);
         if board.squares[to as usize].is_none() {
             if tr == promo_rank {
                 for &promo in &[Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight] {
@@ -71,7 +243,7 @@ fn gen_pawn_moves(board: &Board, from: u8, color: Color, moves: &mut Vec<Move>)
                 // Double push
                 if fr == start_rank {
                     let tr2 = fr + dir * 2;
-                    let to2 = (tr2 * 8 + ff) as u8;
+                    let to2 = make_sq(ff, tr2);
                     if board.squares[to2 as usize].is_none() {
                         moves.push(Move { from, to: to2, promotion: None, captured: None, is_ep: false, is_castle: false });
                     }
@@ -85,7 +257,7 @@ fn gen_pawn_moves(board: &Board, from: u8, color: Color, moves: &mut Vec<Move>)
         let tf = ff + df;
         let tr = fr + dir;
         if tf < 0 || tf >= 8 || tr < 0 || tr >= 8 { continue; }
-        let to = (tr * 8 + tf) as u8;
+        let to = make_sq(tf, tr);
 
         // Normal capture
         if let Some(target) = board.squares[to as usize] {
@@ -106,31 +278,29 @@ fn gen_pawn_moves(board: &Board, from: u8, color: Color, moves: &mut Vec<Move>)
     }
 }
 
-fn gen_leaper_moves(board: &Board, from: u8, color: Color, deltas: &[(i32,i32)], moves: &mut Vec<Move>) {
-    let fr = (from / 8) as i32;
-    let ff = (from % 8) as i32;
+fn gen_leaper_moves<S: MoveSink>(board: &Position, from: u8, color: Color, deltas: &[(i32,i32)], moves: &mut S) {
+    let (fr, ff) = (rank_of(from), file_of(from));
     for &(dr, df) in deltas {
         let tr = fr + dr;
         let tf = ff + df;
         if tr < 0 || tr >= 8 || tf < 0 || tf >= 8 { continue; }
-        let to = (tr * 8 + tf) as u8;
+        let to = make_sq(tf, tr);
         let captured = board.squares[to as usize].and_then(|cp| {
             if cp.color != color { Some(cp.piece) } else { None }
         });
-        if board.squares[to as usize].map_or(true, |cp| cp.color != color) {
+        if board.squares[to as usize].is_none_or(|cp| cp.color != color) {
             moves.push(Move { from, to, promotion: None, captured, is_ep: false, is_castle: false });
         }
     }
 }
 
-fn gen_slider_moves(board: &Board, from: u8, color: Color, dirs: &[(i32,i32)], moves: &mut Vec<Move>) {
-    let fr = (from / 8) as i32;
-    let ff = (from % 8) as i32;
+fn gen_slider_moves<S: MoveSink>(board: &Position, from: u8, color: Color, dirs: &[(i32,i32)], moves: &mut S) {
+    let (fr, ff) = (rank_of(from), file_of(from));
     for &(dr, df) in dirs {
         let mut tr = fr + dr;
         let mut tf = ff + df;
         while tr >= 0 && tr < 8 && tf >= 0 && tf < 8 {
-            let to = (tr * 8 + tf) as u8;
+            let to = make_sq(tf, tr);
             if let Some(cp) = board.squares[to as usize] {
                 if cp.color != color {
                     moves.push(Move { from, to, promotion: None, captured: Some(cp.piece), is_ep: false, is_castle: false });
@@ -144,16 +314,15 @@ fn gen_slider_moves(board: &Board, from: u8, color: Color, dirs: &[(i32,i32)], m
     }
 }
 
-fn gen_castling(board: &Board, from: u8, color: Color, moves: &mut Vec<Move>) {
-    let (ks_bit, qs_bit, king_sq) = match color {
-        Color::White => (0b0001u8, 0b0010u8, 4u8),
-        Color::Black => (0b0100u8, 0b1000u8, 60u8),
-    };
+fn gen_castling<S: MoveSink>(board: &Position, from: u8, color: Color, moves: &mut S) {
+    let king_sq = if color == Color::White { 4u8 } else { 60u8 };
     if from != king_sq { return; }
+    // Rights alone aren't trusted: the rook must actually be at home
+    let rook = Some(ColoredPiece { piece: Piece::Rook, color });
     if board.is_attacked(king_sq, opposite(color)) { return; }
 
     // Kingside
-    if board.castling & ks_bit != 0 {
+    if board.can_castle_ks(color) && board.squares[(king_sq + 3) as usize] == rook {
         let sq1 = king_sq + 1;
         let sq2 = king_sq + 2;
         if board.squares[sq1 as usize].is_none()
@@ -165,7 +334,7 @@ fn gen_castling(board: &Board, from: u8, color: Color, moves: &mut Vec<Move>) {
         }
     }
     // Queenside
-    if board.castling & qs_bit != 0 {
+    if board.can_castle_qs(color) && board.squares[(king_sq - 4) as usize] == rook {
         let sq1 = king_sq - 1;
         let sq2 = king_sq - 2;
         let sq3 = king_sq - 3;
@@ -179,3 +348,49 @@ fn gen_castling(board: &Board, from: u8, color: Color, moves: &mut Vec<Move>) {
         }
     }
 }
+
+// ── Perft ────────────────────────────────────────────────────────────────────
+
+/// Counts leaf nodes of the legal move tree to `depth` (movegen correctness).
+pub fn perft(board: &mut Board, depth: u8) -> u64 {
+    if depth == 0 { return 1; }
+    let moves = generate_moves(board);
+    if depth == 1 { return moves.len() as u64; }
+    let mut nodes = 0;
+    for mv in moves {
+        board.make_move(mv);
+        nodes += perft(board, depth - 1);
+        board.unmake_move();
+    }
+    nodes
+}
+
+/// Perft that also recomputes the Zobrist hash at every leaf and compares
+/// it with the incremental one. Returns (nodes, mismatching leaves).
+pub fn perft_hash(board: &mut Board, depth: u8) -> (u64, u64) {
+    if depth == 0 {
+        let bad = board.hash != crate::zobrist::keys().hash(board);
+        return (1, bad as u64);
+    }
+    let (mut nodes, mut bad) = (0, 0);
+    for mv in generate_moves(board) {
+        board.make_move(mv);
+        let (n, b) = perft_hash(board, depth - 1);
+        board.unmake_move();
+        nodes += n;
+        bad += b;
+    }
+    (nodes, bad)
+}
+
+/// Perft split by root move, for narrowing down a mismatching count.
+pub fn perft_divide(board: &mut Board, depth: u8) -> Vec<(Move, u64)> {
+    let mut out = Vec::new();
+    if depth == 0 { return out; }
+    for mv in generate_moves(board) {
+        board.make_move(mv);
+        out.push((mv, perft(board, depth - 1)));
+        board.unmake_move();
+    }
+    out
+}
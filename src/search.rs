@@ -1,116 +1,208 @@
 // search.rs — Alpha-beta search with proper time management
 
-use crate::board::{Board, Move, Color, Piece};
-use crate::movegen::{generate_moves, generate_captures};
-use crate::eval::evaluate;
-use std::time::Instant;
+use crate::board::{opposite, Board, Move, Piece, Position};
+use crate::movegen::{generate_moves, generate_moves_into, generate_tactical_into, count_legal_moves, has_legal_move, MoveList, MAX_MOVES};
+use crate::eval::{evaluate, TEMPO};
+use crate::book::{time_seed, Book};
+use crate::learn::Learning;
+#[cfg(feature = "syzygy")]
+use crate::tablebase::{piece_total, TablebaseProbe, Wdl};
+use crate::zobrist::Zobrist;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+pub const MAX_PLY: usize = 128; // deepest ply searched; sizes every ply-indexed table
+pub const MAX_SKILL: u8 = 20;   // "Skill Level" at which play is unhandicapped
+pub const MAX_MULTI_PV: usize = 64; // most root moves "MultiPV" may ask for
 
 const INF: i32 = 1_000_000;
 const MATE: i32 = 900_000;
+const CURRMOVE_AFTER_MS: u64 = 3000; // root currmove output starts after this
+const FIRST_CHECK_NODES: u64 = 256;  // early first clock check, for very short searches
+const FAIL_LOW_MARGIN: i32 = 50;      // root score drop that counts as a fail-low
+const ASPIRATION_DEPTH: u8 = 4;       // iterations before this use a full window
+const SKILL_NOISE: i32 = 15;          // cp of root score noise per skill level below max
+const FUTILITY_MARGIN: [i32; 3] = [0, 200, 500]; // by remaining depth (1 and 2 only)
+
+/// True when a new iteration's root score fell far enough below the
+/// previous one that the position needs a closer look
+pub fn is_fail_low(prev_score: i32, score: i32) -> bool {
+    score < prev_score - FAIL_LOW_MARGIN
+}
 
-// ── Zobrist hashing ───────────────────────────────────────────────────────────
-
-pub struct Zobrist {
-    pieces:  [[[u64; 64]; 6]; 2],
-    side:    u64,
-    ep:      [u64; 64],
-    castle:  [u64; 16],
+/// Whether a null move may be tried against `beta`: not in check, not
+/// twice in a row, not in pawn endings where zugzwang makes passing
+/// unsound, and only when the static eval clears beta even after handing
+/// the opponent the move, a swing of twice the tempo bonus
+pub fn null_move_ok(board: &Board, beta: i32) -> bool {
+    beta.abs() < MATE - 1000
+        && board.last_move() != Some(Move::null())
+        && board.has_non_pawn_material()
+        && !board.in_check()
+        && evaluate(board) - 2 * TEMPO >= beta
 }
 
-impl Zobrist {
-    pub fn new() -> Self {
-        let mut s: u64 = 0x123456789abcdef0;
-        let mut r = move || -> u64 {
-            s ^= s << 13; s ^= s >> 7; s ^= s << 17; s
-        };
-        let mut z = Zobrist {
-            pieces:  [[[0u64;64];6];2],
-            side:    r(),
-            ep:      [0u64;64],
-            castle:  [0u64;16],
-        };
-        for c in 0..2 { for p in 0..6 { for sq in 0..64 { z.pieces[c][p][sq] = r(); }}}
-        for i in 0..64 { z.ep[i] = r(); }
-        for i in 0..16 { z.castle[i] = r(); }
-        z
-    }
-
-    pub fn hash(&self, board: &Board) -> u64 {
-        let mut h = 0u64;
-        for sq in 0usize..64 {
-            if let Some(cp) = board.squares[sq] {
-                let pi = match cp.piece {
-                    Piece::Pawn=>0, Piece::Knight=>1, Piece::Bishop=>2,
-                    Piece::Rook=>3, Piece::Queen=>4, Piece::King=>5,
-                };
-                h ^= self.pieces[cp.color as usize][pi][sq];
-            }
-        }
-        if board.side == Color::Black { h ^= self.side; }
-        h ^= self.castle[(board.castling & 15) as usize];
-        if let Some(ep) = board.ep_square { h ^= self.ep[ep as usize]; }
-        h
-    }
+// WDL model: win chance 1 / (1 + e^((WDL_OFFSET - score) / WDL_SCALE)),
+// loss the mirror image, draw the rest. Even scores are ~85% draws.
+const WDL_OFFSET: f64 = 150.0;
+const WDL_SCALE:  f64 = 60.0;
+
+/// Approximate win/draw/loss permilles for the side to move at `score` cp
+pub fn wdl(score: i32) -> (u32, u32, u32) {
+    let s = score.clamp(-2000, 2000) as f64;
+    let win  = (1000.0 / (1.0 + ((WDL_OFFSET - s) / WDL_SCALE).exp())).round() as u32;
+    let loss = (1000.0 / (1.0 + ((WDL_OFFSET + s) / WDL_SCALE).exp())).round() as u32;
+    (win, 1000 - win - loss, loss)
 }
 
 // ── Transposition table ───────────────────────────────────────────────────────
 
+const EXACT_BONUS: u8 = 2; // a bound must be this much deeper to evict an exact entry
+
+// Tablebase wins rank below every mate the search finds itself
+#[cfg(feature = "syzygy")]
+const TB_WIN: i32 = MATE - 2 * MAX_PLY as i32;
+
 #[derive(Clone, Copy)]
 pub struct TTEntry {
     hash:  u64,
     depth: u8,
     score: i32,
     flag:  u8, // 0=exact 1=lower 2=upper
+    age:   u8, // TT generation (search number) that wrote the entry
     mv:    Move,
 }
 
 pub struct TT {
     data: Vec<TTEntry>,
     mask: usize,
+    age:  u8,
 }
 
 impl TT {
     pub fn new() -> Self {
-        let sz = 1 << 20;
+        TT::with_entries(1 << 20)
+    }
+    /// A table of `n` entries, rounded up to a power of two. Tiny tables
+    /// make slot collisions easy to set up in tests.
+    pub fn with_entries(n: usize) -> Self {
+        let sz = n.max(1).next_power_of_two();
         TT {
-            data: vec![TTEntry { hash:0, depth:0, score:0, flag:0, mv: Move::null() }; sz],
+            data: vec![TTEntry { hash:0, depth:0, score:0, flag:0, age:0, mv: Move::null() }; sz],
             mask: sz - 1,
+            age:  0,
         }
     }
+    /// Start a new generation; entries from earlier searches become
+    /// replaceable regardless of depth
+    pub fn new_search(&mut self) {
+        self.age = self.age.wrapping_add(1);
+    }
     pub fn probe(&self, hash: u64) -> Option<&TTEntry> {
         let e = &self.data[hash as usize & self.mask];
         if e.hash == hash && e.depth > 0 { Some(e) } else { None }
     }
+    /// The stored move for `hash`, however shallow the entry: too shallow
+    /// to cut, it is still the best first move to try
+    pub fn best_move(&self, hash: u64) -> Option<Move> {
+        self.probe(hash).map(|e| e.mv).filter(|mv| mv.from != mv.to)
+    }
     pub fn store(&mut self, hash: u64, depth: u8, score: i32, flag: u8, mv: Move) {
         let idx = hash as usize & self.mask;
+        let age = self.age;
         let e = &mut self.data[idx];
-        if e.hash != hash || depth >= e.depth {
-            *e = TTEntry { hash, depth, score, flag, mv };
+        // Keep deep entries from this search; anything stale or shallower goes.
+        // Exact (PV) entries count EXACT_BONUS plies deeper against bounds.
+        let bar = if e.flag == 0 && (flag == 1 || flag == 2) {
+            e.depth.saturating_add(EXACT_BONUS)
+        } else {
+            e.depth
+        };
+        if e.depth == 0 || e.age != age || depth >= bar {
+            *e = TTEntry { hash, depth, score, flag, age, mv };
         }
     }
+    /// Permill of sampled slots holding an entry from the current search
+    pub fn hashfull(&self) -> u32 {
+        let sample = &self.data[..self.data.len().min(1000)];
+        let used = sample.iter().filter(|e| e.depth > 0 && e.age == self.age).count();
+        (used * 1000 / sample.len()) as u32
+    }
     pub fn clear(&mut self) {
         for e in &mut self.data { e.depth = 0; }
     }
     pub fn resize(&mut self, mb: usize) {
-        let sz = (mb * 1024 * 1024 / std::mem::size_of::<TTEntry>()).next_power_of_two();
-        self.data = vec![TTEntry { hash:0, depth:0, score:0, flag:0, mv: crate::board::Move::null() }; sz];
-        self.mask = sz - 1;
+        *self = TT::with_entries(mb * 1024 * 1024 / std::mem::size_of::<TTEntry>());
     }
 }
 
+impl Default for TT {
+    fn default() -> Self { Self::new() }
+}
+
 // ── Search engine ─────────────────────────────────────────────────────────────
 
 pub struct SearchEngine {
     pub tt:      TT,
     pub zob:     Zobrist,
+    pub book:    Book,
+    pub learning: Learning, // LearningFile: past game results nudge root moves
+    #[cfg(feature = "syzygy")]
+    pub tablebase: Option<Box<dyn TablebaseProbe>>, // probed below the root (None = off)
+    #[cfg(feature = "syzygy")]
+    pub tb_hits: u64, // tablebase answers used, last search
     pub nodes:   u64,
-    killer:      [[Option<Move>; 2]; 128],
+    pub debug:   Arc<AtomicBool>, // UCI "debug on": extra info string diagnostics, toggled mid-search
+    // Reproducible runs: depth-only, no clock, fresh tables every search
+    pub deterministic: bool,
+    pub qsee_threshold: i32, // qsearch skips captures whose SEE is below this
+    pub contempt: i32, // centipawns the root side gives up to avoid a draw
+    pub show_wdl: bool, // UCI_ShowWDL: add "wdl W D L" to info lines
+    pub skill_level: u8, // below MAX_SKILL, root moves are picked on noisy scores
+    // Opening variety: early in the game, play any root move within this
+    // many cp of the best (0 = off) for the first `variety_moves` moves
+    pub variety: i32,
+    pub variety_moves: u32,
+    rng:         StdRng, // root move randomisation (skill, variety)
+    exact_root:  bool,   // this search needs true scores for every root move
+    pub search_moves: Vec<Move>, // "go searchmoves": root restricted to these (empty = all)
+    pub node_limit: u64, // "go nodes": stop after this many nodes (0 = no limit)
+    pub multi_pv: usize, // root moves reported per depth, best first (1 = just the best)
+    pub info_interval_ms: u64, // least time between per-depth info lines (0 = every depth)
+    // Pruning switches, for A/B debugging via hidden UCI options
+    pub use_nullmove: bool,
+    pub use_lmr:      bool,
+    pub use_futility: bool, // futility pruning near the leaves, and qsearch delta pruning
+    // Aspiration: root window half-width around the last score (0 = full
+    // window), multiplied by `aspiration_growth` after each fail
+    pub aspiration_window: i32,
+    pub aspiration_growth: i32,
+    killer:      [[Option<Move>; 2]; MAX_PLY],
     history:     [[i32; 64]; 64],
     rep_table:   Vec<u64>,
+    position:    Option<Board>, // set_position: the board `go` searches
+    root_best:   Option<(Move, i32)>, // best fully-searched root move this iteration
+    root_scores: Vec<(Move, i32)>,    // root move scores, this iteration
+    prev_root_scores: Vec<(Move, i32)>, // ... and the last completed one (orders the next)
     // Time management
     start:       Option<Instant>,
     time_limit:  u64, // milliseconds
+    next_check:  u64, // node count at which to look at the clock again
     stopped:     bool,
+    pub stop_signal: Arc<AtomicBool>, // set from another thread (UCI "stop") to end the search
+    pub ponder:  Arc<AtomicBool>, // "go ponder": clock suspended until cleared (ponderhit)
+    pondering:   bool,
+    ponder_ms:   u64, // time spent pondering, not charged to the time limit
+    pub best_move_changes: u32, // root best-move switches in the last search
+    pub aspiration_researches: u32, // root re-searches after a window fail, last search
+    last_nodes:   u64,
+    last_time_ms: u64,
+    last_score:   i32,
+    last_root:    Option<(Position, Move)>, // position and best move of the last search (pv)
 }
 
 impl SearchEngine {
@@ -118,36 +210,213 @@ impl SearchEngine {
         SearchEngine {
             tt:         TT::new(),
             zob:        Zobrist::new(),
+            book:       Book::new(),
+            learning:   Learning::new(),
+            #[cfg(feature = "syzygy")]
+            tablebase:  None,
+            #[cfg(feature = "syzygy")]
+            tb_hits:    0,
             nodes:      0,
-            killer:     [[None; 2]; 128],
+            debug:      Arc::new(AtomicBool::new(false)),
+            deterministic: false,
+            // Slightly negative so near-even trades (BxN etc.) and
+            // desperado captures are still searched
+            qsee_threshold: -50,
+            contempt:   10,
+            show_wdl:   false,
+            skill_level: MAX_SKILL,
+            variety:    0,
+            variety_moves: 10,
+            rng:        StdRng::seed_from_u64(time_seed()),
+            exact_root: false,
+            search_moves: Vec::new(),
+            node_limit: 0,
+            multi_pv:   1,
+            info_interval_ms: 0,
+            use_nullmove: true,
+            use_lmr:      true,
+            use_futility: true,
+            aspiration_window: 35,
+            aspiration_growth: 2,
+            killer:     [[None; 2]; MAX_PLY],
             history:    [[0; 64]; 64],
             rep_table:  Vec::with_capacity(512),
+            position:   None,
+            root_best:  None,
+            root_scores: Vec::with_capacity(MAX_MOVES),
+            prev_root_scores: Vec::with_capacity(MAX_MOVES),
             start:      None,
             time_limit: 5000,
+            next_check: 0,
             stopped:    false,
+            stop_signal: Arc::new(AtomicBool::new(false)),
+            ponder:     Arc::new(AtomicBool::new(false)),
+            pondering:  false,
+            ponder_ms:  0,
+            best_move_changes: 0,
+            aspiration_researches: 0,
+            last_nodes:   0,
+            last_time_ms: 0,
+            last_score:   0,
+            last_root:    None,
         }
     }
 
     pub fn clear(&mut self) {
         self.tt.clear();
         self.nodes = 0;
-        self.killer = [[None; 2]; 128];
+        self.killer = [[None; 2]; MAX_PLY];
         self.history = [[0; 64]; 64];
         self.rep_table.clear();
         self.stopped = false;
     }
 
+    /// Reseeds the root move randomisation (skill level, variety), for
+    /// reproducible play
+    pub fn set_skill_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// Records an earlier game position for repetition detection. Only
+    /// needed for positions the searched board's own move history lacks
+    /// (e.g. a board built from a FEN); that history is always counted.
     pub fn push_position(&mut self, board: &Board) {
-        self.rep_table.push(self.zob.hash(board));
+        self.rep_table.push(board.hash);
+    }
+
+    /// Library counterpart of UCI "position": remembers `board` for `go`.
+    /// Repetitions count against the board's move history alone; positions
+    /// recorded by `push_position` for an earlier board are dropped.
+    pub fn set_position(&mut self, board: &Board) {
+        self.rep_table.clear();
+        self.position = Some(board.clone());
+    }
+
+    /// Searches the board given to `set_position` (the start position if
+    /// none was set)
+    pub fn go(&mut self, max_depth: u8, time_limit_ms: u64) -> (Move, i32) {
+        let mut board = self.position.take().unwrap_or_else(Board::start_pos);
+        let result = self.search(&mut board, max_depth, time_limit_ms);
+        self.position = Some(board);
+        result
+    }
+
+    /// The TT's best move for `board`, if it holds a legal one. No search
+    /// is run; this reports what earlier searches left behind.
+    pub fn tt_best_move(&self, board: &Board) -> Option<Move> {
+        let mv = self.tt.best_move(board.hash)?;
+        self.legal_match(board, mv)
+    }
+
+    /// Principal variation of the last search: its best move, then the TT's
+    /// moves from each following position for as long as they are legal.
+    /// Stops at a repeated position or MAX_PLY moves; empty before any search
+    /// or after one on a position with no legal moves.
+    pub fn pv(&self) -> Vec<Move> {
+        let Some((root, best)) = self.last_root else { return Vec::new() };
+        let mut board = Board::from_position(root);
+        let mut pv = Vec::new();
+        let mut seen = Vec::new();
+        let mut next = self.legal_match(&board, best);
+        while let Some(mv) = next {
+            if pv.len() >= MAX_PLY || seen.contains(&board.hash) { break; }
+            seen.push(board.hash);
+            board.make_move(mv);
+            pv.push(mv);
+            next = self.tt_best_move(&board);
+        }
+        pv
+    }
+
+    fn legal_match(&self, board: &Board, mv: Move) -> Option<Move> {
+        let mut moves = MoveList::new();
+        generate_moves_into(board, &mut moves);
+        moves.iter().copied().find(|m| m.same_motion(&mv))
+    }
+
+    /// Nodes searched by the last completed `search` call
+    pub fn last_nodes(&self) -> u64 {
+        self.last_nodes
+    }
+
+    /// Wall time of the last completed `search` call, in milliseconds
+    pub fn last_time_ms(&self) -> u64 {
+        self.last_time_ms
+    }
+
+    /// Root score of the last completed `search` call, side to move's view
+    pub fn last_score(&self) -> i32 {
+        self.last_score
+    }
+
+    /// Nodes per second of the last completed `search` call
+    pub fn last_nps(&self) -> u64 {
+        self.last_nodes * 1000 / self.last_time_ms.max(1)
     }
 
     fn elapsed_ms(&self) -> u64 {
         self.start.map(|s| s.elapsed().as_millis() as u64).unwrap_or(0)
     }
 
+    // The next check is scheduled from the measured speed so that roughly
+    // an eighth of the remaining time (at most 20ms) passes between checks;
+    // near the deadline that shrinks to every few hundred nodes.
+    // A node limit holds alongside the clock: checks never skip past it,
+    // and whichever runs out first stops the search.
     fn check_time(&mut self) {
-        if self.elapsed_ms() >= self.time_limit {
+        if self.stop_signal.load(Ordering::Relaxed)
+            || (self.node_limit > 0 && self.nodes >= self.node_limit)
+        {
+            self.stopped = true;
+            return;
+        }
+        if self.deterministic || self.still_pondering() {
+            self.next_check = self.cap_check(self.nodes + 4096);
+            return;
+        }
+        let elapsed = self.budget_ms();
+        if elapsed >= self.time_limit {
             self.stopped = true;
+            return;
+        }
+        let nps = self.nodes * 1000 / self.elapsed_ms().max(1);
+        let step_ms = ((self.time_limit - elapsed) / 8).clamp(1, 20);
+        self.next_check = self.cap_check(self.nodes + (nps * step_ms / 1000).clamp(256, 16_384));
+    }
+
+    fn cap_check(&self, next: u64) -> u64 {
+        if self.node_limit > 0 { next.min(self.node_limit) } else { next }
+    }
+
+    // Notices a ponderhit: from then on the clock runs, with the tree built
+    // while pondering kept as it is
+    fn still_pondering(&mut self) -> bool {
+        if self.pondering && !self.ponder.load(Ordering::Relaxed) {
+            self.pondering = false;
+            self.ponder_ms = self.elapsed_ms();
+        }
+        self.pondering
+    }
+
+    // Time charged against the limits: everything since the ponderhit
+    fn budget_ms(&self) -> u64 {
+        self.elapsed_ms() - self.ponder_ms
+    }
+
+    /// Time after which no new iteration is started. The base is half the
+    /// hard limit (the next depth rarely finishes past that); a best move
+    /// that held for several iterations lets us leave early, one that just
+    /// changed earns extra time, and a root fail-low may use it all.
+    pub fn soft_limit(&self, stable_iters: u32, just_changed: bool, fail_low: bool) -> u64 {
+        let base = self.time_limit / 2;
+        if fail_low {
+            self.time_limit
+        } else if just_changed {
+            (base * 3 / 2).min(self.time_limit)
+        } else if stable_iters >= 3 {
+            base * 3 / 5
+        } else {
+            base
         }
     }
 
@@ -157,69 +426,358 @@ impl SearchEngine {
         max_depth: u8,
         time_limit_ms: u64,
     ) -> (Move, i32) {
+        // Weaker levels also see less deep: 1 ply at skill 0, 10 at 19
+        let max_depth = if self.skill_level < MAX_SKILL {
+            max_depth.min(1 + self.skill_level / 2)
+        } else {
+            max_depth
+        };
         self.nodes = 0;
         self.stopped = false;
         self.start = Some(Instant::now());
         self.time_limit = time_limit_ms;
+        self.next_check = self.cap_check(FIRST_CHECK_NODES);
+        self.pondering = self.ponder.load(Ordering::Relaxed);
+        self.ponder_ms = 0;
+        self.best_move_changes = 0;
+        self.aspiration_researches = 0;
+        #[cfg(feature = "syzygy")]
+        { self.tb_hits = 0; }
+        self.root_scores.clear();
+        self.prev_root_scores.clear();
+        self.tt.new_search();
+        if self.deterministic {
+            // Nothing learned in earlier searches may steer this one
+            self.tt.clear();
+            self.killer = [[None; 2]; MAX_PLY];
+            self.history = [[0; 64]; 64];
+        }
 
         let mut best = Move::null();
         let mut best_score = 0;
+        let mut stable_iters = 0;
+        let mut last_info: Option<u64> = None;
+        let mut held_info: Option<String> = None; // throttled, goes out before bestmove
 
-        for depth in 1..=max_depth {
-            let score = self.pvs(board, depth, -INF, INF, 0);
+        if self.debug.load(Ordering::Relaxed) {
+            println!("info string debug hash {:016x} legal {} halfmove {}",
+                self.zob.hash(board), count_legal_moves(board), board.halfmove);
+        }
+
+        // Terminal root: nothing to search. Report why, and answer with the
+        // null move ("bestmove 0000")
+        if !has_legal_move(board) {
+            self.last_nodes = 0;
+            self.last_time_ms = self.elapsed_ms();
+            self.last_score = if board.in_check() { -MATE } else { 0 };
+            self.last_root = None;
+            if board.in_check() {
+                println!("info depth 0 score mate 0");
+                println!("info string checkmate, no legal moves");
+                return (Move::null(), -MATE);
+            }
+            println!("info depth 0 score cp 0");
+            println!("info string stalemate, no legal moves");
+            return (Move::null(), 0);
+        }
+
+        // The game so far (since the last irreversible move) counts towards
+        // threefold alongside the tree: twice in the game plus once in the
+        // search is a draw
+        let pushed = self.rep_table.len();
+        self.rep_table.extend_from_slice(board.reversible_history());
+
+        // Early in the game by the move number, FEN or not
+        let variety = self.variety > 0 && board.game_ply() < self.variety_moves * 2;
+        // Past games' results only steer the final pick, so the root moves
+        // they cover need scores to compare against
+        let learned = !self.learning.is_empty() && generate_moves(board).into_iter().any(|mv| {
+            board.make_move(mv);
+            let nudge = self.learning.nudge(board.hash);
+            board.unmake_move();
+            nudge != 0
+        });
+        self.exact_root = self.skill_level < MAX_SKILL || variety || learned || self.multi_pv > 1;
+        self.root_prepass(board);
 
-            // If stopped mid-search, don't use partial result
-            if self.stopped { break; }
+        for depth in 1..=max_depth {
+            self.root_best = None;
+            if !self.root_scores.is_empty() {
+                self.prev_root_scores = std::mem::take(&mut self.root_scores);
+            }
+            let score = self.aspiration(board, depth, best_score);
+
+            // Stopped mid-iteration: root moves are only recorded once fully
+            // searched, and the first one searched is the previous best (TT
+            // move), so anything recorded is at least as good as `best`.
+            if self.stopped {
+                if let Some((mv, s)) = self.root_best {
+                    best = mv;
+                    best_score = s;
+                }
+                break;
+            }
 
+            let fail_low = depth > 1 && is_fail_low(best_score, score);
             best_score = score;
 
-            let hash = self.zob.hash(board);
-            if let Some(e) = self.tt.probe(hash) {
-                if e.mv.from != e.mv.to { best = e.mv; }
+            let prev_best = best;
+            // The move that last raised alpha at the root; failing that, the
+            // TT's, even if a deeper entry from earlier kept the slot
+            if let Some(mv) = self.root_best.map(|(mv, _)| mv).or_else(|| self.tt.best_move(board.hash)) {
+                best = mv;
+            }
+            let just_changed = depth > 1 && best != prev_best;
+            if just_changed {
+                self.best_move_changes += 1;
+                stable_iters = 0;
+            } else {
+                stable_iters += 1;
             }
 
-            println!("info depth {} score cp {} nodes {} time {} pv {}",
-                depth, score, self.nodes, self.elapsed_ms(), best.to_uci());
+            // evaluate() is side-to-move relative and negamax negates once
+            // per ply, so the root score is already from the mover's view —
+            // exactly what UCI wants; no flip for Black.
+            let elapsed = self.elapsed_ms();
+            // MultiPV: the best few root moves by their exact scores, one
+            // line each (the root only holds the searchmoves, if any)
+            let lines: Vec<(Move, i32)> = if self.multi_pv > 1 {
+                let mut ranked = self.root_scores.clone();
+                ranked.sort_by_key(|&(_, s)| -s);
+                ranked.truncate(self.multi_pv);
+                ranked
+            } else {
+                vec![(best, score)]
+            };
+            let info = lines.iter().enumerate().map(|(k, &(mv, score))| {
+                let multipv = if self.multi_pv > 1 { format!(" multipv {}", k + 1) } else { String::new() };
+                let wdl = if self.show_wdl {
+                    let (w, d, l) = wdl(score);
+                    format!(" wdl {} {} {}", w, d, l)
+                } else {
+                    String::new()
+                };
+                if self.deterministic {
+                    // Leave out the clock-dependent fields so runs compare equal
+                    format!("info depth {}{} score cp {}{} nodes {} hashfull {} pv {}",
+                        depth, multipv, score, wdl, self.nodes, self.tt.hashfull(), mv.to_uci())
+                } else {
+                    format!("info depth {}{} score cp {}{} nodes {} nps {} hashfull {} time {} pv {}",
+                        depth, multipv, score, wdl, self.nodes, self.nodes * 1000 / elapsed.max(1),
+                        self.tt.hashfull(), elapsed, mv.to_uci())
+                }
+            }).collect::<Vec<_>>().join("\n");
+            // Throttling goes by the clock, so deterministic runs print every depth
+            let due = self.deterministic
+                || last_info.is_none_or(|t| elapsed >= t + self.info_interval_ms);
+            if due {
+                println!("{}", info);
+                last_info = Some(elapsed);
+                held_info = None;
+            } else {
+                held_info = Some(info);
+            }
+            if fail_low {
+                println!("info string fail-low at depth {}, extending time", depth);
+            }
 
             if score.abs() > MATE - 1000 { break; }
 
-            // Stop if we've used more than half our time — next depth won't finish
-            if self.elapsed_ms() >= self.time_limit / 2 { break; }
+            if !self.deterministic && !self.still_pondering()
+                && self.budget_ms() >= self.soft_limit(stable_iters, just_changed, fail_low) { break; }
+        }
+
+        self.rep_table.truncate(pushed);
+        // The last completed depth is always reported
+        if let Some(info) = held_info { println!("{}", info); }
+
+        let pick = if self.skill_level < MAX_SKILL {
+            self.skill_pick()
+        } else if variety {
+            self.variety_pick()
+        } else if learned {
+            self.learned_pick(board)
+        } else {
+            None
+        };
+        if let Some((mv, s)) = pick {
+            best = mv;
+            best_score = s;
         }
 
-        // Fallback
+        // UCI: no bestmove while pondering, even if the search has finished
+        while self.still_pondering() && !self.stop_signal.load(Ordering::Relaxed) {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        // Fallback: stopped before any root move was fully searched. Take
+        // the move the root ordering would have searched first (the root is
+        // not terminal, so there is one).
         if best.from == best.to {
-            let moves = generate_moves(board);
+            let mut moves = MoveList::new();
+            generate_moves_into(board, &mut moves);
+            if moves.iter().any(|m| self.search_moves.contains(m)) {
+                moves.retain(|m| self.search_moves.contains(m));
+            }
+            self.order_root(&mut moves, self.tt.best_move(board.hash));
             if let Some(&m) = moves.first() { best = m; }
         }
 
+        self.last_nodes = self.nodes;
+        self.last_time_ms = self.elapsed_ms();
+        self.last_score = best_score;
+        self.last_root = Some((board.position(), best));
         (best, best_score)
     }
 
+    // One iteration at the root. From ASPIRATION_DEPTH on the window starts
+    // narrow around the previous score and widens on the failing side until
+    // the score lands inside it. Mate scores get the full window.
+    fn aspiration(&mut self, board: &mut Board, depth: u8, prev: i32) -> i32 {
+        let mut window = self.aspiration_window;
+        let (mut alpha, mut beta) = if depth >= ASPIRATION_DEPTH && window > 0
+            && prev.abs() < MATE - 1000
+        {
+            (prev - window, prev + window)
+        } else {
+            (-INF, INF)
+        };
+        loop {
+            self.root_scores.clear();
+            let score = self.pvs(board, depth, alpha, beta, 0);
+            if self.stopped { return score; }
+            window = window.saturating_mul(self.aspiration_growth.max(2));
+            if score <= alpha && alpha > -INF {
+                alpha = score.saturating_sub(window).max(-INF);
+            } else if score >= beta && beta < INF {
+                beta = score.saturating_add(window).min(INF);
+            } else {
+                return score;
+            }
+            self.aspiration_researches += 1;
+        }
+    }
+
+    // Weakened play: the root move with the best score after adding up to
+    // ±SKILL_NOISE cp of noise per level below MAX_SKILL. The further a
+    // move is below the best, the rarer the noise lifts it to the top.
+    fn skill_pick(&mut self) -> Option<(Move, i32)> {
+        let spread = (MAX_SKILL - self.skill_level.min(MAX_SKILL)) as i32 * SKILL_NOISE;
+        let scores = if self.root_scores.is_empty() { &self.prev_root_scores } else { &self.root_scores };
+        let mut pick = None;
+        let mut pick_noisy = -INF;
+        for &(mv, s) in scores {
+            let noisy = s + self.rng.gen_range(-spread..=spread);
+            if noisy > pick_noisy {
+                pick = Some((mv, s));
+                pick_noisy = noisy;
+            }
+        }
+        pick
+    }
+
+    // Opening variety: any root move within `variety` cp of the best, each
+    // equally likely
+    fn variety_pick(&mut self) -> Option<(Move, i32)> {
+        let scores = if self.root_scores.is_empty() { &self.prev_root_scores } else { &self.root_scores };
+        let top = scores.iter().map(|&(_, s)| s).max()?;
+        let close: Vec<(Move, i32)> = scores.iter().copied()
+            .filter(|&(_, s)| s >= top - self.variety).collect();
+        Some(close[self.rng.gen_range(0..close.len())])
+    }
+
+    // Learning: the best root move once each is nudged by past games'
+    // results for the position it leads to. The nudge never reaches the
+    // reported score or the TT. Mates are left as they are.
+    fn learned_pick(&self, board: &mut Board) -> Option<(Move, i32)> {
+        let scores = if self.root_scores.is_empty() { &self.prev_root_scores } else { &self.root_scores };
+        scores.iter().copied().max_by_key(|&(mv, s)| {
+            if s.abs() >= MATE - 1000 { return s; }
+            board.make_move(mv);
+            let nudge = self.learning.nudge(board.hash);
+            board.unmake_move();
+            s + nudge
+        })
+    }
+
     fn is_draw(&self, hash: u64, halfmove: u32) -> bool {
         if halfmove >= 100 { return true; }
+        // Only the game and the current line: a draw by repetition depends
+        // on the path, so it is never cached in the TT
         self.rep_table.iter().filter(|&&h| h == hash).count() >= 2
     }
 
+    /// Remember a quiet move that caused a cutoff at `ply` (newest first).
+    /// Captures and promotions are ordered ahead of killers anyway, so
+    /// they are not stored.
+    pub fn store_killer(&mut self, ply: usize, mv: Move) {
+        if !mv.is_quiet() { return; }
+        let Some(slot) = self.killer.get_mut(ply) else { return };
+        if slot[0].is_some_and(|k| k.same_motion(&mv)) { return; }
+        slot[1] = slot[0];
+        slot[0] = Some(mv);
+    }
+
+    pub fn killers(&self, ply: usize) -> [Option<Move>; 2] {
+        self.killer.get(ply).copied().unwrap_or([None; 2])
+    }
+
+    /// Score of a draw for the side to move at `ply`. Only a draw reached
+    /// straight from the root carries contempt (the root side sees
+    /// -contempt); deeper draws score 0 so the tree stays stable.
+    pub fn draw_score(&self, ply: usize) -> i32 {
+        if ply == 1 { self.contempt } else { 0 }
+    }
+
     fn pvs(&mut self, board: &mut Board, depth: u8,
            mut alpha: i32, beta: i32, ply: usize) -> i32 {
+        if self.stopped { return 0; } // unwinding: not a node
         self.nodes += 1;
 
-        // Check time every 2048 nodes
-        if self.nodes & 2047 == 0 { self.check_time(); }
+        if self.nodes >= self.next_check { self.check_time(); }
         if self.stopped { return 0; }
+        if ply >= MAX_PLY { return evaluate(board); }
 
-        let hash = self.zob.hash(board);
+        let hash = board.hash;
 
-        if ply > 0 && self.is_draw(hash, board.halfmove) { return 0; }
+        if ply > 0 && self.is_draw(hash, board.halfmove) {
+            // Checkmate takes precedence over the fifty-move rule, even when
+            // the mating move is the 100th ply
+            if board.halfmove >= 100 && board.in_check() && !has_legal_move(board) {
+                return -MATE + ply as i32;
+            }
+            return self.draw_score(ply);
+        }
 
-        // TT lookup
+        // Tablebase: an exact result ends the search here. The tables know
+        // nothing of castling, so positions with rights left are searched.
+        // The score depends on the ply, so it is not stored in the TT.
+        #[cfg(feature = "syzygy")]
+        if ply > 0 && board.castling == 0 {
+            if let Some(wdl) = self.tablebase.as_ref()
+                .filter(|tb| piece_total(board) <= tb.max_pieces())
+                .and_then(|tb| tb.probe_wdl(board))
+            {
+                self.tb_hits += 1;
+                let score = match wdl {
+                    Wdl::Win  => TB_WIN - ply as i32,
+                    Wdl::Loss => -TB_WIN + ply as i32,
+                    _ => self.draw_score(ply),
+                };
+                return score;
+            }
+        }
+
+        // TT lookup. A hit too shallow to cut still supplies the first move.
+        let tt_mv = self.tt.best_move(hash);
         if let Some(e) = self.tt.probe(hash) {
-            if e.depth >= depth {
+            // Never cut at the root: an aspiration re-search must visit and
+            // record the root moves
+            if ply > 0 && e.depth >= depth {
                 match e.flag {
                     0 => return e.score,
-                    1 => if e.score >= beta  { return e.score; }
-                    2 => if e.score <= alpha { return e.score; }
+                    1 if e.score >= beta  => return e.score,
+                    2 if e.score <= alpha => return e.score,
                     _ => {}
                 }
             }
@@ -229,26 +787,71 @@ impl SearchEngine {
             return self.qsearch(board, alpha, beta);
         }
 
-        let moves = generate_moves(board);
+        // Null move pruning: if passing still fails high, a real move will too
+        if self.use_nullmove && ply > 0 && depth >= 3 && null_move_ok(board, beta) {
+            board.make_null_move();
+            let s = -self.pvs(board, depth - 3, -beta, -beta + 1, ply + 1);
+            board.unmake_null_move();
+            if self.stopped { return 0; }
+            if s >= beta { return beta; }
+        }
+
+        // Futility: a node this close to the leaves whose static eval is
+        // far below alpha will not be lifted by a quiet move
+        let futile = self.use_futility && ply > 0 && depth <= 2
+            && alpha.abs() < MATE - 1000
+            && !board.in_check()
+            && evaluate(board) + FUTILITY_MARGIN[depth as usize] <= alpha;
+
+        // This frame's move buffer — lives on the stack, one per ply
+        let mut moves = MoveList::new();
+        generate_moves_into(board, &mut moves);
         if moves.is_empty() {
+            // Stalemate is a plain 0 at any ply; contempt only applies to
+            // repetition and fifty-move draws
             return if board.in_check() { -MATE + ply as i32 } else { 0 };
         }
+        if ply == 0 && moves.iter().any(|m| self.search_moves.contains(m)) {
+            moves.retain(|m| self.search_moves.contains(m));
+        }
 
-        let ordered = self.order(moves, hash, ply);
-        let mut best_mv = ordered[0];
+        if ply == 0 {
+            self.order_root(&mut moves, tt_mv);
+        } else {
+            self.order(&mut moves, tt_mv, ply);
+        }
+        let mut best_mv = moves[0];
         let mut raised_alpha = false;
 
         self.rep_table.push(hash);
 
-        for (i, &mv) in ordered.iter().enumerate() {
+        for (i, &mv) in moves.iter().enumerate() {
+            if ply == 0 && !self.deterministic && self.elapsed_ms() >= CURRMOVE_AFTER_MS {
+                println!("info depth {} currmove {} currmovenumber {}", depth, mv.to_uci(), i + 1);
+            }
+
+            if futile && i > 0 && mv.is_quiet() && !board.gives_check(mv) { continue; }
+
             board.make_move(mv);
+            // The generator only hands out legal moves; catch it if it doesn't
+            debug_assert!(board.find_king(opposite(board.side))
+                .is_none_or(|k| !board.is_attacked(k, board.side)),
+                "illegal move {} leaves the king in check at ply {}", mv.to_uci(), ply);
+
+            if self.debug.load(Ordering::Relaxed) && board.hash != self.zob.hash(board) {
+                println!("info string debug incremental hash mismatch after {} at ply {}", mv.to_uci(), ply);
+            }
 
-            let score = if i == 0 {
+            let score = if ply == 0 && self.exact_root {
+                // Skill and variety pick among root moves by score, and
+                // MultiPV reports several, so every one needs its true
+                // score rather than a bound
+                -self.pvs(board, depth - 1, -beta, INF, ply + 1)
+            } else if i == 0 {
                 -self.pvs(board, depth - 1, -beta, -alpha, ply + 1)
             } else {
-                let r: u8 = if i >= 3 && depth >= 3
-                    && mv.captured.is_none()
-                    && mv.promotion.is_none()
+                let r: u8 = if self.use_lmr && i >= 3 && depth >= 3
+                    && mv.is_quiet()
                     && !board.in_check()
                 { 1 } else { 0 };
 
@@ -260,6 +863,12 @@ impl SearchEngine {
             };
 
             board.unmake_move();
+            if ply == 0 && !self.stopped { self.root_scores.push((mv, score)); }
+
+            // Hash consistency: make/unmake must restore the exact position
+            if self.debug.load(Ordering::Relaxed) && self.zob.hash(board) != hash {
+                println!("info string debug hash mismatch after {} at ply {}", mv.to_uci(), ply);
+            }
 
             if self.stopped { self.rep_table.pop(); return 0; }
 
@@ -267,11 +876,11 @@ impl SearchEngine {
                 alpha = score;
                 best_mv = mv;
                 raised_alpha = true;
+                if ply == 0 { self.root_best = Some((mv, score)); }
 
                 if score >= beta {
-                    if mv.captured.is_none() && ply < 128 {
-                        self.killer[ply][1] = self.killer[ply][0];
-                        self.killer[ply][0] = Some(mv);
+                    if mv.is_quiet() {
+                        self.store_killer(ply, mv);
                         let h = &mut self.history[mv.from as usize][mv.to as usize];
                         *h = (*h + depth as i32 * depth as i32).min(50_000);
                     }
@@ -289,16 +898,27 @@ impl SearchEngine {
     }
 
     fn qsearch(&mut self, board: &mut Board, mut alpha: i32, beta: i32) -> i32 {
+        if self.stopped { return 0; }
         self.nodes += 1;
+        if self.nodes >= self.next_check { self.check_time(); }
         if self.stopped { return 0; }
 
+        // Standing pat is only a floor: a pending promotion can raise it,
+        // and queening moves are among the tactical moves searched below.
+        // Failing high on it is sound, since moving can only add to it.
         let stand_pat = evaluate(board);
         if stand_pat >= beta { return beta; }
         if stand_pat > alpha { alpha = stand_pat; }
 
-        for mv in generate_captures(board) {
-            let gain = mv.captured.map(|p| crate::board::piece_value(p)).unwrap_or(0);
-            if stand_pat + gain + 200 < alpha { continue; }
+        let mut captures = MoveList::new();
+        generate_tactical_into(board, &mut captures);
+        for &mv in captures.iter() {
+            let promo = mv.promotion.map_or(0, |p| {
+                crate::board::piece_value(p) - crate::board::piece_value(Piece::Pawn)
+            });
+            let gain = mv.captured.map(crate::board::piece_value).unwrap_or(0) + promo;
+            if self.use_futility && stand_pat + gain + 200 < alpha { continue; }
+            if board.see(mv) < self.qsee_threshold { continue; }
             board.make_move(mv);
             let s = -self.qsearch(board, -beta, -alpha);
             board.unmake_move();
@@ -308,22 +928,91 @@ impl SearchEngine {
         alpha
     }
 
-    fn order(&self, mut moves: Vec<Move>, hash: u64, ply: usize) -> Vec<Move> {
-        let tt_mv = self.tt.probe(hash).map(|e| e.mv);
-        moves.sort_by_cached_key(|mv| {
+    /// Root moves with their scores from the most recent iteration, in the
+    /// order they were searched
+    pub fn root_scores(&self) -> &[(Move, i32)] {
+        if self.root_scores.is_empty() { &self.prev_root_scores } else { &self.root_scores }
+    }
+
+    // Root pre-pass: a one-ply look past each root move, so the first
+    // iteration is ordered by more than the move itself. The opponent's
+    // reply is its static eval or, if better, that plus its best capture by
+    // SEE, so a move that leaves a piece hanging sinks. The scores stand in
+    // for a previous iteration's; no search is involved, so the clock and
+    // node budget are not charged.
+    fn root_prepass(&mut self, board: &mut Board) {
+        let mut moves = MoveList::new();
+        generate_moves_into(board, &mut moves);
+        if moves.iter().any(|m| self.search_moves.contains(m)) {
+            moves.retain(|m| self.search_moves.contains(m));
+        }
+        let mut replies = MoveList::new();
+        for &mv in moves.iter() {
+            board.make_move(mv);
+            generate_tactical_into(board, &mut replies);
+            let threat = replies.iter().map(|&r| board.see(r)).max().unwrap_or(0).max(0);
+            let score = -(evaluate(board) + threat);
+            board.unmake_move();
+            self.prev_root_scores.push((mv, score));
+        }
+    }
+
+    // Root ordering: the TT move (last iteration's best) first, the rest by
+    // the previous iteration's per-move scores (the pre-pass's, on the
+    // first). Fail-low moves tie at the old alpha, so the TT move needs its
+    // own bonus to stay in front.
+    fn order_root(&self, moves: &mut MoveList, tt_mv: Option<Move>) {
+        let mut scores = [0i32; MAX_MOVES];
+        for (i, mv) in moves.iter().enumerate() {
+            scores[i] = if tt_mv.is_some_and(|m| m.same_motion(mv)) { INF } else {
+                self.prev_root_scores.iter()
+                    .find(|(m, _)| m.same_motion(mv))
+                    .map_or(-INF, |&(_, s)| s)
+            };
+        }
+        sort_by_scores(moves, &mut scores);
+    }
+
+    // Sorts best-first in place. Scores are computed once, then an insertion
+    // sort (stable, allocation-free) orders the moves by them.
+    fn order(&self, moves: &mut MoveList, tt_mv: Option<Move>, ply: usize) {
+        let score = |mv: &Move| {
             let mut s = 0i32;
-            if Some(*mv) == tt_mv { s += 2_000_000; }
+            let is = |m: Option<Move>| m.is_some_and(|m| m.same_motion(mv));
+            if is(tt_mv) { s += 2_000_000; }
             if let Some(cap) = mv.captured {
                 s += 1_000_000 + crate::board::piece_value(cap) * 10 - 100;
             }
             if mv.promotion == Some(Piece::Queen) { s += 900_000; }
-            if ply < 128 {
-                if self.killer[ply][0] == Some(*mv) { s += 800_000; }
-                if self.killer[ply][1] == Some(*mv) { s += 700_000; }
+            if ply < MAX_PLY {
+                if is(self.killer[ply][0]) { s += 800_000; }
+                if is(self.killer[ply][1]) { s += 700_000; }
             }
             s += self.history[mv.from as usize][mv.to as usize].min(600_000);
-            -s
-        });
-        moves
+            s
+        };
+
+        let mut scores = [0i32; MAX_MOVES];
+        for (i, mv) in moves.iter().enumerate() { scores[i] = score(mv); }
+        sort_by_scores(moves, &mut scores);
     }
 }
+
+// Stable insertion sort of `moves` by descending score (no allocation)
+fn sort_by_scores(moves: &mut MoveList, scores: &mut [i32; MAX_MOVES]) {
+    for i in 1..moves.len() {
+        let (mv, s) = (moves[i], scores[i]);
+        let mut j = i;
+        while j > 0 && scores[j - 1] < s {
+            moves[j] = moves[j - 1];
+            scores[j] = scores[j - 1];
+            j -= 1;
+        }
+        moves[j] = mv;
+        scores[j] = s;
+    }
+}
+
+impl Default for SearchEngine {
+    fn default() -> Self { Self::new() }
+}
@@ -0,0 +1,44 @@
+// tablebase.rs — Endgame tablebase probing hook
+//
+// The search asks a `TablebaseProbe` for the win/draw/loss value of small
+// positions and takes the answer instead of searching further. Reading the
+// actual tables (Syzygy or otherwise) is up to the implementation plugged
+// into `SearchEngine::tablebase`. None ships with the crate, so only library
+// users who supply one get probes; the UCI binary never probes.
+
+use crate::board::{Board, Color, Piece};
+use std::io;
+
+/// Result for the side to move, in Syzygy's five classes. Cursed wins and
+/// blessed losses are decided by the fifty-move rule, so they score as draws.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Wdl { Loss, BlessedLoss, Draw, CursedWin, Win }
+
+pub trait TablebaseProbe: Send {
+    /// Positions with more pieces (kings included) than this are not probed
+    fn max_pieces(&self) -> u32;
+    /// The position's value, or None if the tables don't cover it
+    fn probe_wdl(&self, board: &Board) -> Option<Wdl>;
+}
+
+/// Pieces on the board, kings included
+pub fn piece_total(board: &Board) -> u32 {
+    [Color::White, Color::Black].iter().flat_map(|&c| {
+        [Piece::Pawn, Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen, Piece::King]
+            .map(|p| board.piece_count(c, p) as u32)
+    }).sum()
+}
+
+/// Syzygy WDL tables (`*.rtbw`) in the directory `path`, and the most
+/// pieces any of them covers (from the names: "KRPvKR" is five). What the
+/// SyzygyPath option reports; only the names are read.
+pub fn scan_syzygy_dir(path: &str) -> io::Result<(usize, u32)> {
+    let (mut tables, mut most) = (0, 0);
+    for entry in std::fs::read_dir(path)? {
+        let name = entry?.file_name();
+        let Some(stem) = name.to_str().and_then(|n| n.strip_suffix(".rtbw")) else { continue };
+        tables += 1;
+        most = most.max(stem.chars().filter(|c| c.is_ascii_uppercase()).count() as u32);
+    }
+    Ok((tables, most))
+}
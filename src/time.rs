@@ -0,0 +1,89 @@
+// time.rs — Per-move time allocation from the UCI "go" parameters
+
+use crate::board::{Board, Color};
+
+pub const DEFAULT_MOVE_OVERHEAD: u64 = 30;
+
+// Never plan to search for less than this, however tight the clock
+const MIN_SEARCH_MS: u64 = 10;
+
+/// Returns (max_depth, time_limit_ms). `move_overhead` is reserved off every
+/// timed search to cover GUI and network latency; `min_think` is the least a
+/// clock-based allocation may come to. Every limit given applies:
+/// "go depth 30 movetime 500" stops at whichever is reached first. A "nodes"
+/// limit is enforced by the search itself; here it only stands in for the
+/// clock when no other limit is given.
+pub fn pick_time(line: &str, board: &Board, move_overhead: u64, min_think: u64) -> (u8, u64) {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+
+    let depth = get_val(&parts, "depth");
+    let max_depth = depth.map_or(12, |d| d.min(12) as u8);
+
+    // Infinite — search deep with lots of time
+    if line.contains("infinite") {
+        return (max_depth, 300_000);
+    }
+
+    let time_key = if board.side == Color::White { "wtime" } else { "btime" };
+    let movetime = get_val(&parts, "movetime");
+    let nodes = get_val(&parts, "nodes");
+    let mut limit = 300_000; // explicit depth or nodes alone: give plenty of time
+
+    // Movetime — use exactly that much time, less the overhead
+    if let Some(mt) = movetime {
+        limit = limit.min(mt.saturating_sub(move_overhead).max(MIN_SEARCH_MS));
+    }
+
+    // The clock, when given, or when nothing else bounds the search
+    let bounded = depth.is_some() || movetime.is_some() || nodes.is_some();
+    if get_val(&parts, time_key).is_some() || !bounded {
+        limit = limit.min(clock_time(&parts, board, move_overhead, min_think));
+    }
+
+    (max_depth, limit)
+}
+
+// Clock-based time management
+fn clock_time(parts: &[&str], board: &Board, move_overhead: u64, min_think: u64) -> u64 {
+    let time_key = if board.side == Color::White { "wtime" } else { "btime" };
+    let inc_key  = if board.side == Color::White { "winc"  } else { "binc"  };
+    let opp_key  = if board.side == Color::White { "btime" } else { "wtime" };
+    let movestogo_key = "movestogo";
+
+    let clock_ms = get_val(parts, time_key).unwrap_or(10_000);
+    let inc_ms   = get_val(parts, inc_key).unwrap_or(0);
+    let movestogo = get_val(parts, movestogo_key);
+    let opp_ms   = get_val(parts, opp_key).unwrap_or(clock_ms);
+
+    // How much time to spend this move:
+    // Use clock/movestogo + a fraction of increment. With no movestogo the
+    // clock has to last the game: expect about 50 more moves in the opening,
+    // down to 20 in the endgame, and lean on the increment, which all comes
+    // back
+    let alloc = match movestogo {
+        Some(mtg) => (clock_ms / mtg.max(1)) + inc_ms * 3 / 4,
+        None => {
+            let moves_left = 20 + 30 * board.phase() as u64 / 256;
+            clock_ms / moves_left + inc_ms
+        }
+    };
+
+    // Ahead on the clock: spend part of the lead, up to half as much again
+    let lead = clock_ms.saturating_sub(opp_ms);
+    let alloc = alloc + (alloc * lead / clock_ms.max(1)).min(alloc / 2);
+
+    // Never use more than 1/3 of remaining clock
+    let alloc = alloc.min(clock_ms / 3);
+
+    // Safety margin
+    let alloc = alloc.saturating_sub(move_overhead).max(MIN_SEARCH_MS);
+
+    // Minimum Thinking Time, still within the 1/3-clock cap
+    alloc.max(min_think.min(clock_ms / 3))
+}
+
+pub fn get_val(parts: &[&str], key: &str) -> Option<u64> {
+    parts.iter().position(|&p| p == key)
+        .and_then(|i| parts.get(i+1))
+        .and_then(|s| s.parse().ok())
+}
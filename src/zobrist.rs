@@ -0,0 +1,72 @@
+// zobrist.rs — Zobrist keys, shared by the board's incremental hash and search
+
+use std::sync::OnceLock;
+
+use crate::board::{Color, ColoredPiece, Position};
+
+pub struct Zobrist {
+    pieces:  [[[u64; 64]; 6]; 2],
+    side:    u64,
+    ep:      [u64; 64],
+    castle:  [u64; 16],
+}
+
+impl Zobrist {
+    pub fn new() -> Self {
+        let mut s: u64 = 0x123456789abcdef0;
+        let mut r = move || -> u64 {
+            s ^= s << 13; s ^= s >> 7; s ^= s << 17; s
+        };
+        let mut z = Zobrist {
+            pieces:  [[[0u64;64];6];2],
+            side:    r(),
+            ep:      [0u64;64],
+            castle:  [0u64;16],
+        };
+        for c in 0..2 { for p in 0..6 { for sq in 0..64 { z.pieces[c][p][sq] = r(); }}}
+        for i in 0..64 { z.ep[i] = r(); }
+        for i in 0..16 { z.castle[i] = r(); }
+        z
+    }
+
+    /// Full recomputation — the reference the incremental hash must match
+    pub fn hash(&self, board: &Position) -> u64 {
+        let mut h = 0u64;
+        for sq in 0u8..64 {
+            if let Some(cp) = board.squares[sq as usize] {
+                h ^= self.piece(cp, sq);
+            }
+        }
+        if board.side == Color::Black { h ^= self.side; }
+        h ^= self.castle(board.castling);
+        h ^= self.ep(board.ep_square);
+        h
+    }
+
+    pub fn piece(&self, cp: ColoredPiece, sq: u8) -> u64 {
+        self.pieces[cp.color as usize][cp.piece as usize][sq as usize]
+    }
+
+    pub fn side(&self) -> u64 {
+        self.side
+    }
+
+    pub fn castle(&self, rights: u8) -> u64 {
+        self.castle[(rights & 15) as usize]
+    }
+
+    pub fn ep(&self, ep_square: Option<u8>) -> u64 {
+        ep_square.map_or(0, |sq| self.ep[sq as usize])
+    }
+}
+
+impl Default for Zobrist {
+    fn default() -> Self { Self::new() }
+}
+
+/// Process-wide keys; `Zobrist::new` is deterministic, so these match any
+/// other instance (the search and book keep their own).
+pub fn keys() -> &'static Zobrist {
+    static KEYS: OnceLock<Zobrist> = OnceLock::new();
+    KEYS.get_or_init(Zobrist::new)
+}
@@ -0,0 +1,497 @@
+// board.rs — Board state bookkeeping: repetition and related history
+
+use chess_engine::board::{file_of, Board, Color, Move, Piece, BK};
+use chess_engine::eval::evaluate;
+use chess_engine::movegen::generate_moves;
+
+fn play(board: &mut Board, moves: &str) {
+    for mv in moves.split_whitespace() {
+        assert!(board.make_uci_move(mv), "illegal {}", mv);
+    }
+}
+
+#[test]
+fn threefold_after_pawn_move() {
+    let mut board = Board::start_pos();
+    play(&mut board, "e2e3 e7e6");
+    play(&mut board, "g1f3 g8f6 f3g1 f6g8");
+    assert!(!board.is_repetition());
+    play(&mut board, "g1f3 g8f6 f3g1 f6g8");
+    assert!(board.is_repetition());
+}
+
+#[test]
+fn positions_before_pawn_move_do_not_count() {
+    let mut board = Board::start_pos();
+    let start = board.hash;
+    play(&mut board, "g1f3 g8f6 f3g1 f6g8");
+    assert_eq!(board.hash, start);
+    assert!(!board.is_repetition());
+
+    // The pawn moves put the start position out of reach: the scan stops there
+    play(&mut board, "e2e3 e7e6");
+    assert!(board.reversible_history().is_empty());
+
+    // Two more shuffles: the position after e6 is seen a second time, then
+    // a third, counting only from the pawn moves on
+    play(&mut board, "g1f3 g8f6 f3g1 f6g8");
+    assert!(!board.is_repetition());
+    assert!(!board.reversible_history().contains(&start));
+    play(&mut board, "g1f3 g8f6 f3g1 f6g8");
+    assert!(board.is_repetition());
+    assert_eq!(board.reversible_history().len(), 8);
+}
+
+#[test]
+fn short_fens_default_the_missing_fields() {
+    let full = Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+    let four = Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3");
+    assert_eq!(four.position(), full.position());
+    assert_eq!((four.halfmove, four.fullmove()), (0, 1));
+
+    let five = Board::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 12");
+    assert_eq!((five.halfmove, five.fullmove()), (12, 1));
+    assert!(five.can_castle_ks(Color::White));
+
+    // Placement only: White to move, castling from the kings and rooks at home
+    let bare = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR");
+    assert_eq!(bare.position(), Board::start_pos().position());
+
+    // Nothing at all is an empty board, not a panic
+    let empty = Board::from_fen("");
+    assert!(empty.squares.iter().all(|sq| sq.is_none()));
+    assert_eq!(generate_moves(&empty).len(), 0);
+}
+
+#[test]
+fn oversized_move_counters_are_capped() {
+    // Past 2^31 the ply count used to overflow; the counters are capped instead
+    let mut huge = Board::from_fen("4k3/8/8/8/8/8/8/4K3 b - - 4294967295 4294967295");
+    assert_eq!((huge.halfmove, huge.fullmove()), (1_000_000, 1_000_000));
+    assert!(huge.make_uci_move("e8d8") && huge.make_uci_move("e1d1"));
+    assert_eq!(huge.fullmove(), 1_000_001);
+    assert_eq!(Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 2147483649").fullmove(), 1_000_000);
+}
+
+#[test]
+fn fullmove_counts_on_from_the_fen() {
+    let mut board = Board::from_fen("4k3/8/8/8/8/8/8/4K3 b - - 0 30");
+    assert_eq!((board.fullmove(), board.game_ply()), (30, 59));
+    play(&mut board, "e8d8");
+    assert_eq!((board.fullmove(), board.game_ply()), (31, 60));
+    play(&mut board, "e1d1");
+    assert_eq!(board.fullmove(), 31);
+    board.unmake_move();
+    board.unmake_move();
+    assert_eq!(board.fullmove(), 30);
+    assert_eq!(Board::start_pos().game_ply(), 0);
+}
+
+#[test]
+fn killer_matches_despite_stale_capture() {
+    let board = Board::from_fen("4k3/8/8/3p4/8/8/8/3RK3 w - - 0 1");
+    let capture = board.parse_uci("d1d5").unwrap();
+    assert_eq!(capture.captured, Some(Piece::Pawn));
+
+    // The same rook move remembered from a position where d5 was empty
+    let killer = Move { captured: None, ..capture };
+    assert_ne!(killer, capture);
+    assert!(killer.same_motion(&capture));
+
+    let other = board.parse_uci("d1d4").unwrap();
+    assert!(!killer.same_motion(&other));
+    let promo = Move { promotion: Some(Piece::Queen), ..killer };
+    assert!(!promo.same_motion(&killer));
+}
+
+#[test]
+fn en_passant_is_not_quiet() {
+    let board = Board::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1");
+    let ep = board.parse_uci("e5d6").unwrap();
+    assert!(ep.is_ep);
+    assert!(!ep.is_quiet());
+    // Even if the capture were not recorded on the move
+    assert!(!Move { captured: None, ..ep }.is_quiet());
+
+    assert!(board.parse_uci("e5e6").unwrap().is_quiet());
+    assert!(board.parse_uci("e1d2").unwrap().is_quiet());
+    let promo = Board::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1").parse_uci("a7a8q").unwrap();
+    assert!(!promo.is_quiet());
+}
+
+// Every move three plies deep from a few tactical positions
+fn check_gives_check(board: &mut Board, depth: u8) {
+    for mv in generate_moves(board) {
+        let predicted = board.gives_check(mv);
+        board.make_move(mv);
+        assert_eq!(predicted, board.in_check(), "gives_check wrong for {}", mv.to_uci());
+        if depth > 1 { check_gives_check(board, depth - 1); }
+        board.unmake_move();
+    }
+}
+
+#[test]
+fn gives_check_matches_make_move() {
+    for fen in [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        // Discovered check by en passant, and castling with check
+        "8/8/8/R2pP2k/8/8/8/4K3 w - d6 0 1",
+        "5k2/8/8/8/8/8/8/4K2R w K - 0 1",
+    ] {
+        let mut board = Board::from_fen(fen);
+        check_gives_check(&mut board, 3);
+    }
+    let ep = Board::from_fen("8/8/8/R2pP2k/8/8/8/4K3 w - d6 0 1");
+    assert!(ep.gives_check(ep.parse_uci("e5d6").unwrap()));
+}
+
+// Phase recounted from the squares, as game_phase once did
+fn phase_from_scratch(board: &Board) -> i32 {
+    let units: i32 = board.squares.iter().flatten().map(|cp| match cp.piece {
+        Piece::Knight | Piece::Bishop => 1,
+        Piece::Rook => 2,
+        Piece::Queen => 4,
+        _ => 0,
+    }).sum();
+    (units * 256 / 24).clamp(0, 256)
+}
+
+#[test]
+fn incremental_phase_tracks_captures_and_promotion() {
+    use chess_engine::eval::game_phase;
+
+    let mut board = Board::start_pos();
+    // Minor and queen trades, then a pawn captures its way through to promote
+    let line = "e2e4 d7d5 e4d5 d8d5 b1c3 d5a5 d2d4 c7c6 g1f3 c8g4 f1e2 g4f3 e2f3 \
+                a5c3 b2c3 e7e5 d4e5 f8b4 c3b4 b8d7 e5e6 g8f6 e6f7 e8e7 f7f8q";
+    let mut played = 0;
+    for mv in line.split_whitespace() {
+        assert!(board.make_uci_move(mv), "illegal {}", mv);
+        played += 1;
+        assert_eq!(board.phase(), phase_from_scratch(&board), "after {}", mv);
+        assert_eq!(game_phase(&board), board.phase());
+    }
+    for _ in 0..played {
+        board.unmake_move();
+        assert_eq!(board.phase(), phase_from_scratch(&board));
+    }
+    assert_eq!(board.phase(), 256);
+}
+
+#[test]
+fn piece_counts_and_material_match_a_square_scan() {
+    use chess_engine::board::piece_value;
+
+    const PIECES: [Piece; 6] =
+        [Piece::Pawn, Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen, Piece::King];
+    let check = |b: &Board, after: &str| {
+        for color in [Color::White, Color::Black] {
+            let mut material = 0;
+            for piece in PIECES {
+                let n = b.squares.iter().flatten()
+                    .filter(|cp| cp.color == color && cp.piece == piece).count();
+                assert_eq!(b.piece_count(color, piece) as usize, n, "{:?} {:?} after {}", color, piece, after);
+                if piece != Piece::King { material += n as i32 * piece_value(piece); }
+            }
+            assert_eq!(b.material(color), material, "{:?} after {}", color, after);
+        }
+    };
+
+    // Trades of every kind, a capture into a queen, and a knight promotion
+    // that is taken at once
+    let mut board = Board::start_pos();
+    let line = "e2e4 d7d5 e4d5 d8d5 b1c3 d5a5 d2d4 c7c6 g1f3 c8g4 f1e2 g4f3 e2f3 \
+                a5c3 b2c3 e7e5 d4e5 f8b4 c3b4 b8d7 e5e6 g8f6 e6f7 e8e7 f7f8q \
+                h8f8 b4b5 a7a6 b5c6 b7b6 c6c7 a6a5 c7c8n a8c8";
+    check(&board, "start");
+    let mut played = 0;
+    for mv in line.split_whitespace() {
+        assert!(board.make_uci_move(mv), "illegal {}", mv);
+        played += 1;
+        check(&board, mv);
+    }
+    for _ in 0..played {
+        board.unmake_move();
+        check(&board, "unmake");
+    }
+    check(&board.mirror(), "mirror");
+}
+
+#[test]
+fn capturing_promotion_round_trip() {
+    use chess_engine::board::{Color, ColoredPiece};
+
+    for (fen, uci, pawn, victim, color) in [
+        ("r3k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7a8n", 49, 56, Color::White),
+        ("4k3/8/8/8/8/8/1p6/R3K3 b - - 0 1", "b2a1q", 9, 0, Color::Black),
+    ] {
+        let mut board = Board::from_fen(fen);
+        let before = board.clone();
+        assert!(board.make_uci_move(uci), "illegal {}", uci);
+        assert_eq!(board.squares[pawn], None);
+        assert_eq!(board.squares[victim].map(|cp| cp.color), Some(color));
+
+        board.unmake_move();
+        assert_eq!(board.squares, before.squares, "{}", uci);
+        assert_eq!(board.squares[pawn], Some(ColoredPiece { piece: Piece::Pawn, color }));
+        assert_eq!(board.squares[victim].map(|cp| (cp.piece, cp.color)),
+            Some((Piece::Rook, if color == Color::White { Color::Black } else { Color::White })));
+        assert_eq!(board.hash, before.hash);
+        assert_eq!(board.phase(), before.phase());
+        for c in [Color::White, Color::Black] {
+            for p in [Piece::Pawn, Piece::Knight, Piece::Queen, Piece::Rook] {
+                assert_eq!(board.piece_count(c, p), before.piece_count(c, p));
+            }
+        }
+    }
+}
+
+#[test]
+fn hash_follows_en_passant_squares() {
+    use chess_engine::zobrist::keys;
+
+    // Double pushes set an ep square; the next move, a pawn step, a piece
+    // move, a null move or the ep capture itself, clears it again
+    let mut board = Board::start_pos();
+    let line = [
+        ("e2e4", Some(20)), ("d7d6", None), ("e4e5", None), ("f7f5", Some(45)),
+        ("e5f6", None), ("g8f6", None), ("d2d4", Some(19)), ("c7c5", Some(42)),
+        ("d4c5", None), ("b7b5", Some(41)), ("c5b6", None),
+    ];
+    for (mv, ep) in line {
+        assert!(board.make_uci_move(mv), "illegal {}", mv);
+        assert_eq!(board.ep_square, ep, "after {}", mv);
+        assert_eq!(board.hash, keys().hash(&board), "after {}", mv);
+        if ep.is_some() {
+            board.make_null_move();
+            assert_eq!(board.ep_square, None);
+            assert_eq!(board.hash, keys().hash(&board), "null after {}", mv);
+            board.unmake_null_move();
+            assert_eq!(board.hash, keys().hash(&board), "null undone after {}", mv);
+        }
+    }
+    for _ in 0..line.len() {
+        board.unmake_move();
+        assert_eq!(board.hash, keys().hash(&board));
+    }
+    assert_eq!(board.hash, Board::start_pos().hash);
+}
+
+#[test]
+fn capture_promotion_in_uci_and_san() {
+    // gxh8=Q takes the rook on its home square, and with it Black's short castling
+    let fen = "r3kn1r/6P1/8/8/8/8/8/4K3 w kq - 0 1";
+    let before = Board::from_fen(fen);
+    let mv = generate_moves(&before).into_iter()
+        .find(|m| m.to_uci() == "g7h8q").expect("g7h8q generated");
+    assert_eq!(mv.captured, Some(Piece::Rook));
+    assert_eq!(mv.promotion, Some(Piece::Queen));
+    assert_eq!(before.to_san(mv), "gxh8=Q");
+
+    let mut by_uci = before.clone();
+    let mut by_san = before.clone();
+    assert!(by_uci.make_uci_move("g7h8q"));
+    assert!(by_san.make_san_move("gxh8=Q"));
+    assert_eq!(by_uci.position(), by_san.position());
+    assert_eq!(by_uci.castling, before.castling & !BK);
+
+    by_uci.unmake_move();
+    assert_eq!(by_uci.position(), before.position());
+    assert_eq!(by_uci.piece_count(Color::White, Piece::Queen), 0);
+    assert_eq!(by_uci.piece_count(Color::Black, Piece::Rook), 2);
+}
+
+#[test]
+fn halfmove_clock_after_each_move_kind() {
+    let clock_after = |fen: &str, mv: &str| {
+        let mut board = Board::from_fen(fen);
+        assert!(board.make_uci_move(mv), "illegal {}", mv);
+        board.halfmove
+    };
+    let fen = "r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 5 30";
+    assert_eq!(clock_after(fen, "a1b1"), 6);  // quiet
+    assert_eq!(clock_after(fen, "e1g1"), 6);  // castling is reversible
+    assert_eq!(clock_after(fen, "e5d6"), 0);  // en passant
+    assert_eq!(clock_after(fen, "e5e6"), 0);  // pawn push
+    assert_eq!(clock_after(fen, "b7b8q"), 0); // promotion
+    assert_eq!(clock_after(fen, "b7a8n"), 0); // capturing promotion
+    assert_eq!(clock_after(fen, "a1a8"), 0);  // capture
+
+    // Unmaking restores the old clock
+    let mut board = Board::from_fen(fen);
+    assert!(board.make_uci_move("e5d6"));
+    board.unmake_move();
+    assert_eq!(board.halfmove, 5);
+}
+
+#[test]
+fn square_helpers_round_trip() {
+    use chess_engine::board::{file_of, make_sq, rank_of, sq_from_str, sq_to_str};
+
+    for sq in 0u8..64 {
+        let (file, rank) = (file_of(sq), rank_of(sq));
+        assert!((0..8).contains(&file) && (0..8).contains(&rank));
+        assert_eq!(make_sq(file, rank), sq);
+        assert_eq!(sq_from_str(&sq_to_str(sq)), Some(sq));
+    }
+    assert_eq!(sq_to_str(0), "a1");
+    assert_eq!(sq_to_str(7), "h1");
+    assert_eq!(sq_to_str(36), "e5");
+    assert_eq!(sq_to_str(63), "h8");
+    assert_eq!(make_sq(4, 3), 28); // e4
+    assert_eq!(sq_from_str("i1"), None);
+    assert_eq!(sq_from_str("a9"), None);
+}
+
+#[test]
+fn malformed_moves_do_not_panic() {
+    let start = Board::start_pos();
+    let bad = Move { from: 200, to: 200, promotion: None, captured: None, is_ep: false, is_castle: false };
+    for mv in [
+        bad,
+        Move { to: 12, ..bad },
+        Move { from: 12, ..bad },
+        Move { from: 35, to: 250, is_ep: true, ..bad },
+        Move { from: 20, to: 28, ..bad },                  // empty from-square
+    ] {
+        let mut board = start.clone();
+        board.make_move(mv);
+        assert_eq!(board.squares, start.squares);
+        board.unmake_move();
+        assert_eq!(board.squares, start.squares);
+        assert_eq!(board.hash, start.hash);
+        assert_eq!(board.side, start.side);
+        assert!(board.make_uci_move("e2e4"));
+    }
+
+    // A "castle" whose rook square would lie off the board moves only the piece
+    let start = Board::from_fen("4k3/8/8/8/8/8/8/1N2K3 w - - 0 1");
+    let mut board = start.clone();
+    board.make_move(Move { from: 1, to: 0, is_castle: true, ..bad });
+    board.unmake_move();
+    assert_eq!(board.squares, start.squares);
+    assert_eq!(board.hash, start.hash);
+}
+
+#[test]
+fn continues_tells_a_new_game_apart() {
+    let mut game = Board::start_pos();
+    for uci in ["e2e4", "e7e5"] { game.make_uci_move(uci); }
+
+    // The same game, a few moves on, replayed from the start
+    let mut later = game.clone();
+    for uci in ["g1f3", "b8c6", "f1b5"] { later.make_uci_move(uci); }
+    assert!(later.continues(&game));
+
+    // Sent as a bare FEN: two plies on (1.e4 e5 2.Nf3 Nc6) still counts,
+    // four (3.Bc4 Nf6 as well) does not
+    let two = Board::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3");
+    assert!(two.continues(&game));
+    let four = Board::from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4");
+    assert!(!four.continues(&game));
+
+    // An unrelated position, or a fresh game from the start
+    assert!(!Board::from_fen("8/8/4k3/8/8/4K3/8/8 w - - 0 1").continues(&game));
+    assert!(!Board::start_pos().continues(&later));
+}
+
+#[test]
+fn pawn_file_counts_follow_make_and_unmake() {
+    let rescan = |b: &Board| {
+        let mut files = [[0u8; 8]; 2];
+        for sq in 0u8..64 {
+            if let Some(cp) = b.squares[sq as usize].filter(|cp| cp.piece == Piece::Pawn) {
+                files[cp.color as usize][file_of(sq) as usize] += 1;
+            }
+        }
+        files
+    };
+    let counts = |b: &Board| {
+        let mut files = [[0u8; 8]; 2];
+        for color in [Color::White, Color::Black] {
+            for f in 0..8 { files[color as usize][f as usize] = b.pawns_on_file(color, f); }
+        }
+        files
+    };
+
+    // exd5 takes a pawn, then axb8=Q captures and promotes
+    let mut board = Board::from_fen("1r2k3/P7/8/3p4/4P3/8/8/4K3 w - - 0 1");
+    assert_eq!(counts(&board), rescan(&board));
+    for uci in ["e4d5", "e8d7", "a7b8q"] {
+        assert!(board.make_uci_move(uci), "{}", uci);
+        assert_eq!(counts(&board), rescan(&board), "after {}", uci);
+    }
+    // En passant
+    let mut ep = Board::from_fen("4k3/8/8/8/3p4/8/4P3/4K3 w - - 0 1");
+    for uci in ["e2e4", "d4e3"] {
+        assert!(ep.make_uci_move(uci), "{}", uci);
+        assert_eq!(counts(&ep), rescan(&ep), "after {}", uci);
+    }
+    for _ in 0..3 {
+        board.unmake_move();
+        assert_eq!(counts(&board), rescan(&board));
+    }
+    ep.unmake_move();
+    assert_eq!(counts(&ep), rescan(&ep));
+    assert_eq!(counts(&board.mirror()), rescan(&board.mirror()));
+}
+
+#[test]
+fn position_round_trips_through_board() {
+    let mut board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+    play(&mut board, "a2a4");
+    let pos = board.position();
+    assert_eq!(pos.ep_square, board.ep_square);
+
+    let back = Board::from_position(pos);
+    assert_eq!(back.position(), pos);
+    assert_eq!(back.halfmove, 0);
+    assert!(back.position_hashes.is_empty());
+    // Counts are rebuilt, so the rebuilt board plays on like the original
+    for color in [Color::White, Color::Black] {
+        for piece in [Piece::Pawn, Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen, Piece::King] {
+            assert_eq!(back.piece_count(color, piece), board.piece_count(color, piece));
+        }
+    }
+    assert_eq!(back.phase(), board.phase());
+    assert_eq!(generate_moves(&back), generate_moves(&board));
+
+    // Movegen and eval run on the bare position, and a move played on it
+    // lands where the board's does
+    assert_eq!(generate_moves(&pos), generate_moves(&board));
+    assert_eq!(evaluate(&pos), evaluate(&board));
+    let mut after = pos;
+    let reply = board.parse_uci("b4a3").unwrap();
+    after.make_move(reply);
+    board.make_move(reply);
+    assert_eq!(after, board.position());
+}
+
+#[test]
+fn uci_and_san_give_the_same_board() {
+    let apply = |moves: &str| {
+        let mut board = Board::start_pos();
+        for mv in moves.split_whitespace() {
+            assert!(board.make_move_str(mv), "{}", mv);
+        }
+        board
+    };
+    let (uci, san) = (apply("e2e4"), apply("e4"));
+    assert_eq!(uci.position(), san.position());
+    assert_eq!(uci.position_hashes, san.position_hashes);
+
+    let uci = apply("e2e4 e7e5 g1f3 b8c6 f1b5 a7a6 e1g1");
+    let san = apply("e4 e5 Nf3 Nc6 Bb5 a6 O-O");
+    assert_eq!(uci.position(), san.position());
+    assert_eq!(uci.halfmove, san.halfmove);
+
+    // Neither notation: nothing is played
+    let mut board = Board::start_pos();
+    assert!(!board.make_move_str("e5"));
+    assert!(!board.make_move_str("e2e5"));
+    assert_eq!(board.position(), Board::start_pos().position());
+}
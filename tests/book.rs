@@ -0,0 +1,79 @@
+// book.rs — Opening book built from PGN games, and its seeded selection
+
+use chess_engine::board::Board;
+use chess_engine::book::Book;
+
+// Replays `moves` (SAN) from the start position
+fn after(moves: &str) -> Board {
+    let mut board = Board::start_pos();
+    for san in moves.split_whitespace() {
+        assert!(board.make_san_move(san), "{}", san);
+    }
+    board
+}
+
+fn weight(book: &Book, board: &Board, uci: &str) -> u32 {
+    book.candidates(board).iter().find(|(m, _)| m == uci).map_or(0, |&(_, w)| w)
+}
+
+#[test]
+fn transposed_games_add_up() {
+    let pgn = r#"
+[Event "One"]
+[Result "1-0"]
+
+1. d4 Nf6 2. c4 e6 3. Nc3 Bb4 {Nimzo} 1-0
+
+[Event "Two"]
+[Result "0-1"]
+
+1. c4 e6 2. d4 Nf6 $1 (2... d5 3. Nc3) 3. Nc3 d5 0-1
+"#;
+    let book = Book::build_from_pgn(pgn, 1);
+
+    // Both games reach this position and play Nc3 from it
+    let shared = after("d4 Nf6 c4 e6");
+    assert_eq!(shared.hash, after("c4 e6 d4 Nf6").hash);
+    assert_eq!(weight(&book, &shared, "b1c3"), 2);
+    // After that they part ways, one game each
+    let nimzo = after("d4 Nf6 c4 e6 Nc3");
+    assert_eq!((weight(&book, &nimzo, "f8b4"), weight(&book, &nimzo, "d7d5")), (1, 1));
+    // The first moves differ, and the variation is left out
+    let start = Board::start_pos();
+    assert_eq!((weight(&book, &start, "d2d4"), weight(&book, &start, "c2c4")), (1, 1));
+    assert_eq!(weight(&book, &after("c4 e6 d4"), "d7d5"), 0);
+}
+
+#[test]
+fn transposition_through_a_double_step_adds_up() {
+    // One order ends on the double step ...d5, the other on ...e6, so only
+    // one has an en passant square; no pawn can take there: one position
+    let pgn = "1. d4 d5 2. c4 e6 3. Nc3 *\n1. c4 e6 2. d4 d5 3. Nc3 *\n";
+    let book = Book::build_from_pgn(pgn, 1);
+    assert_eq!(weight(&book, &after("d4 d5 c4 e6"), "b1c3"), 2);
+    assert_eq!(weight(&book, &after("c4 e6 d4 d5"), "b1c3"), 2);
+
+    // The probe finds the pooled moves either way
+    let mut book = book;
+    assert_eq!(book.probe(&after("c4 e6 d4 d5")).map(|m| m.to_uci()).as_deref(), Some("b1c3"));
+}
+
+#[test]
+fn same_seed_same_book_moves() {
+    // Twenty picks from the start position, where the book has several moves
+    let picks = |book: &mut Book| -> Vec<String> {
+        let start = Board::start_pos();
+        (0..20).map(|_| book.probe(&start).expect("book move").to_uci()).collect()
+    };
+    let first = picks(&mut Book::with_seed(42));
+    assert_eq!(picks(&mut Book::with_seed(42)), first);
+    assert!(first.iter().any(|m| *m != first[0]), "{:?}", first);
+
+    // Reseeding restarts the same sequence; another seed takes another path
+    let mut book = Book::with_seed(7);
+    let other = picks(&mut book);
+    assert_ne!(other, first);
+    book.set_seed(42);
+    assert_eq!(picks(&mut book), first);
+    assert_eq!(book.seed(), 42);
+}
@@ -0,0 +1,105 @@
+// castling.rs — Castling-rights helpers and castling move generation
+
+use chess_engine::board::{Board, Color, BK, BQ, WK, WQ};
+use chess_engine::movegen::generate_moves;
+
+#[test]
+fn helpers_match_raw_bits() {
+    let mut board = Board::start_pos();
+    for rights in 0u8..16 {
+        board.castling = rights;
+        assert_eq!(board.can_castle_ks(Color::White), rights & 0b0001 != 0, "{:04b}", rights);
+        assert_eq!(board.can_castle_qs(Color::White), rights & 0b0010 != 0, "{:04b}", rights);
+        assert_eq!(board.can_castle_ks(Color::Black), rights & 0b0100 != 0, "{:04b}", rights);
+        assert_eq!(board.can_castle_qs(Color::Black), rights & 0b1000 != 0, "{:04b}", rights);
+
+        board.remove_castle(Color::White);
+        assert_eq!(board.castling, rights & !0b0011, "{:04b}", rights);
+        board.castling = rights;
+        board.remove_castle(Color::Black);
+        assert_eq!(board.castling, rights & !0b1100, "{:04b}", rights);
+    }
+    assert_eq!((WK, WQ, BK, BQ), (0b0001, 0b0010, 0b0100, 0b1000));
+}
+
+#[test]
+fn no_castling_without_rook() {
+    let castles = |fen: &str| {
+        let mut board = Board::from_fen(fen);
+        board.castling = WK | WQ | BK | BQ; // claimed regardless of the rooks
+        generate_moves(&board).into_iter()
+            .filter(|m| m.is_castle).map(|m| m.to_uci()).collect::<Vec<_>>()
+    };
+
+    assert_eq!(castles("r3k3/8/8/8/8/8/8/R3K3 w - - 0 1"), ["e1c1"]);
+    assert_eq!(castles("r3k3/8/8/8/8/8/8/R3K3 b - - 0 1"), ["e8c8"]);
+    assert!(castles("4k2r/8/8/8/8/8/8/4K3 w - - 0 1").is_empty());
+    assert_eq!(castles("4k2r/8/8/8/8/8/8/4K3 b - - 0 1"), ["e8g8"]);
+}
+
+#[test]
+fn king_takes_rook_castling_accepted() {
+    let fen = "r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w KQkq - 0 1";
+    let board = Board::from_fen(fen);
+
+    let standard = board.parse_uci("e1g1").expect("e1g1");
+    let rook_form = board.parse_uci("e1h1").expect("e1h1");
+    assert!(standard.is_castle);
+    assert_eq!(standard, rook_form);
+    assert_eq!(board.parse_uci("e1a1"), board.parse_uci("e1c1"));
+
+    let mut board = Board::from_fen(fen);
+    assert!(board.make_uci_move("e1h1"));
+    assert!(board.make_uci_move("e8a8"));
+    let after = Board::from_fen("2kr3r/pppppppp/8/8/8/8/PPPPPPPP/R4RK1 w - - 2 2");
+    assert_eq!(board.squares, after.squares);
+}
+
+#[test]
+fn fen_rights_without_home_pieces_are_dropped() {
+    // Claims K, but the king is on d1
+    let board = Board::from_fen("4k3/8/8/8/8/8/8/3K3R w K - 0 1");
+    assert_eq!(board.castling, 0);
+    assert!(!generate_moves(&board).iter().any(|m| m.is_castle));
+
+    // Only the rights with king and rook at home survive
+    let board = Board::from_fen("r3k3/8/8/8/8/8/8/R3K1R1 w KQkq - 0 1");
+    assert_eq!(board.castling, WQ | BQ);
+    assert_eq!(Board::start_pos().castling, WK | WQ | BK | BQ);
+}
+
+#[test]
+fn capturing_a_home_rook_drops_its_right() {
+    // White's h1 rook captured: White keeps only the queenside right
+    let mut board = Board::from_fen("r3k2r/8/8/8/8/8/6b1/R3K2R b KQkq - 0 1");
+    let before = board.clone();
+    assert!(board.make_uci_move("g2h1"));
+    assert!(!board.can_castle_ks(Color::White));
+    assert!(board.can_castle_qs(Color::White));
+    assert_eq!(board.castling, WQ | BK | BQ);
+    assert_eq!(board.hash, Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2b w Qkq - 0 1").hash);
+
+    // And a1, from the other side
+    let mut board = Board::from_fen("r3k2r/8/8/8/8/8/1b6/R3K2R b KQkq - 0 1");
+    assert!(board.make_uci_move("b2a1"));
+    assert_eq!(board.castling, WK | BK | BQ);
+
+    // Black's corners, by White pieces
+    let mut board = Board::from_fen("r3k2r/1B4B1/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    assert!(board.make_uci_move("b7a8"));
+    assert!(board.make_uci_move("e8d8"));
+    assert!(board.make_uci_move("g7h8"));
+    assert_eq!(board.castling, WK | WQ);
+
+    // A promotion capture landing on a corner counts the same
+    let mut board = Board::from_fen("r3k2r/6P1/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    assert!(board.make_uci_move("g7h8q"));
+    assert_eq!(board.castling, WK | WQ | BQ);
+
+    // Unmaking the capture gives the right back
+    let mut board = before.clone();
+    let mv = board.parse_uci("g2h1").unwrap();
+    board.make_move(mv);
+    board.unmake_move();
+    assert_eq!((board.castling, board.hash), (before.castling, before.hash));
+}
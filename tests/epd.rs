@@ -0,0 +1,31 @@
+// epd.rs — EPD parsing for test suites
+
+use chess_engine::board::{Board, Color};
+use chess_engine::epd::{epd_moves, parse_epd};
+
+#[test]
+fn parses_wac_record() {
+    let line = r#"2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4BK1 w - - bm Qg6; id "WAC.001";"#;
+    let (board, ops) = parse_epd(line);
+
+    let fen = Board::from_fen("2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4BK1 w - - 0 1");
+    assert_eq!(board.hash, fen.hash);
+    assert_eq!(board.side, Color::White);
+    assert_eq!(ops["id"], "WAC.001");
+    assert_eq!(ops["bm"], "Qg6");
+
+    let bm: Vec<String> = epd_moves(&board, &ops, "bm").iter().map(|m| m.to_uci()).collect();
+    assert_eq!(bm, ["g3g6"]);
+    assert!(epd_moves(&board, &ops, "am").is_empty());
+}
+
+#[test]
+fn parses_multiple_moves_and_quoted_semicolons() {
+    let line = r#"r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - am Ng5 h3; c0 "odd; comment"; id "x";"#;
+    let (board, ops) = parse_epd(line);
+    assert_eq!(board.castling, 0b1111);
+    assert_eq!(ops["c0"], "odd; comment");
+    assert_eq!(ops["id"], "x");
+    let am: Vec<String> = epd_moves(&board, &ops, "am").iter().map(|m| m.to_uci()).collect();
+    assert_eq!(am, ["f3g5", "h2h3"]);
+}
@@ -0,0 +1,247 @@
+// eval.rs — Evaluation invariants
+
+use chess_engine::board::{Board, Color, Piece};
+use chess_engine::eval::{attacks, bishop_pair, king_zone, eval_features, evaluate, evaluate_white, game_phase, EvalWeights, TEMPO};
+
+#[test]
+fn phase_spans_opening_to_endgame() {
+    assert_eq!(game_phase(&Board::start_pos()), 256);
+    assert_eq!(game_phase(&Board::from_fen("4k3/pppppppp/8/8/8/8/PPPPPPPP/4K3 w - - 0 1")), 0);
+    // Extra queens from promotion stay clamped
+    assert_eq!(game_phase(&Board::from_fen("qqqqk3/8/8/8/8/8/8/QQQQK3 w - - 0 1")), 256);
+    // Queens off: 16 of 24 units
+    assert_eq!(game_phase(&Board::from_fen("rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNB1KBNR w KQkq - 0 1")),
+        16 * 256 / 24);
+}
+
+#[test]
+fn evaluation_is_color_symmetric() {
+    for fen in [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 0 1",
+        "2kr3r/ppp2ppp/2n5/8/8/5N2/PPP2PPP/2KR3R w - - 0 1",
+        "6k1/5ppp/8/8/8/8/1P6/6K1 w - - 0 1",
+    ] {
+        let board = Board::from_fen(fen);
+        let mirrored = board.mirror();
+        // Side-to-move relative, tempo included on both sides
+        assert_eq!(evaluate(&board), evaluate(&mirrored), "{}", fen);
+        assert_eq!(evaluate_white(&board), -evaluate_white(&mirrored), "{}", fen);
+        assert_eq!(mirrored.mirror().hash, board.hash, "{}", fen);
+    }
+}
+
+#[test]
+fn pawn_race_square_rule() {
+    // a5 queens in three moves; from h1 the king needs seven, from c6 two
+    let outside = evaluate_white(&Board::from_fen("8/8/8/P7/8/8/8/K6k w - - 0 1"));
+    let inside  = evaluate_white(&Board::from_fen("8/8/2k5/P7/8/8/8/K7 w - - 0 1"));
+    assert!(outside - inside > 300, "outside {} vs inside {}", outside, inside);
+
+    // From e6 the king is one step outside the square: Black to move gets
+    // in, White to move wins the race
+    let black_to_move = evaluate_white(&Board::from_fen("8/8/4k3/P7/8/8/8/K7 b - - 0 1"));
+    let white_to_move = evaluate_white(&Board::from_fen("8/8/4k3/P7/8/8/8/K7 w - - 0 1"));
+    assert!(white_to_move - black_to_move > 300, "{} vs {}", white_to_move, black_to_move);
+
+    // Connected passers beat split ones
+    let connected = evaluate_white(&Board::from_fen("4k3/8/8/3PP3/8/8/8/4K3 w - - 0 1"));
+    let split     = evaluate_white(&Board::from_fen("4k3/8/8/1P3P2/8/8/8/4K3 w - - 0 1"));
+    assert!(connected > split, "connected {} vs split {}", connected, split);
+}
+
+#[test]
+fn rook_on_seventh_needs_a_confined_king() {
+    // What the rook gains on a7 over a6, with the black king on g8 or e5
+    let gain = |on_7th: &str, on_6th: &str| {
+        evaluate_white(&Board::from_fen(on_7th)) - evaluate_white(&Board::from_fen(on_6th))
+    };
+    let confined = gain("6k1/R7/8/8/8/8/5PPP/6K1 w - - 0 1", "6k1/8/R7/8/8/8/5PPP/6K1 w - - 0 1");
+    let central  = gain("8/R7/8/4k3/8/8/5PPP/6K1 w - - 0 1", "8/8/R7/4k3/8/8/5PPP/6K1 w - - 0 1");
+    assert_eq!(confined - central, 25);
+}
+
+#[test]
+fn attack_map_matches_is_attacked() {
+    for fen in [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    ] {
+        let board = Board::from_fen(fen);
+        for color in [Color::White, Color::Black] {
+            let map = attacks(&board, color).squares;
+            for sq in 0u8..64 {
+                assert_eq!(map >> sq & 1 == 1, board.is_attacked(sq, color), "{} {:?} {}", fen, color, sq);
+            }
+        }
+    }
+    // Mobility: 4 knight moves each at the start, nothing else can move
+    let start = Board::start_pos();
+    assert_eq!(attacks(&start, Color::White).mobility, 4);
+}
+
+#[test]
+fn shared_attack_maps_match_square_by_square_terms() {
+    let enemy = |c: Color| if c == Color::White { Color::Black } else { Color::White };
+    for fen in [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        "r1bqk2r/pppp1ppp/2n2n2/2b1p1N1/2B1P3/8/PPPP1PPP/RNBQK2R w KQkq - 6 5",
+        "6k1/5ppp/4n3/8/2B5/8/5PPP/3R2K1 w - - 0 1",
+    ] {
+        let board = Board::from_fen(fen);
+        // King safety: the enemy's map over the king zone
+        for color in [Color::White, Color::Black] {
+            let king = board.find_king(color).unwrap();
+            let zone = king_zone(king);
+            let map = attacks(&board, enemy(color)).squares;
+            let scanned = (0u8..64)
+                .filter(|&sq| zone >> sq & 1 == 1 && board.is_attacked(sq, enemy(color)))
+                .count() as u32;
+            assert_eq!((zone & map).count_ones(), scanned, "{} {:?}", fen, color);
+        }
+        // Threats: pieces attacked and undefended, found with is_attacked
+        let hanging = |color: Color| (0u8..64).filter(|&sq| board.squares[sq as usize].is_some_and(|cp| {
+            cp.color == enemy(color) && cp.piece != Piece::Pawn && cp.piece != Piece::King
+                && board.is_attacked(sq, color) && !board.is_attacked(sq, enemy(color))
+        })).count() as i32;
+        assert_eq!(eval_features(&board).threats, 15 * (hanging(Color::White) - hanging(Color::Black)), "{}", fen);
+    }
+}
+
+#[test]
+fn wrong_bishop_rook_pawn_is_a_draw() {
+    // Light-squared bishop, h-pawn, dark h8 corner held by the black king
+    let fortress = Board::from_fen("7k/8/8/7P/8/8/4B3/6K1 w - - 0 1");
+    assert_eq!(evaluate_white(&fortress), 0);
+    assert_eq!(evaluate_white(&fortress.mirror()), 0);
+    // Doubled rook pawns change nothing
+    assert_eq!(evaluate_white(&Board::from_fen("6k1/8/8/7P/7P/8/4B3/6K1 w - - 0 1")), 0);
+
+    // The right bishop, or a king too far from the corner, still wins
+    assert!(evaluate_white(&Board::from_fen("7k/8/8/7P/8/8/3B4/6K1 w - - 0 1")) > 200);
+    assert!(evaluate_white(&Board::from_fen("8/8/8/2k4P/8/8/4B3/6K1 w - - 0 1")) > 200);
+}
+
+#[test]
+fn queen_sortie_costs_more_before_development() {
+    // What Qh4 costs against Qd1, with the minors at home and developed
+    let cost = |home: &str, out: &str| {
+        evaluate_white(&Board::from_fen(home)) - evaluate_white(&Board::from_fen(out))
+    };
+    let undeveloped = cost("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2",
+                           "rnbqkbnr/pppp1ppp/8/4p3/4P2Q/8/PPPP1PPP/RNB1KBNR w KQkq - 0 2");
+    let developed   = cost("rnbqkbnr/pppp1ppp/8/4p3/2B1P3/2N1BN2/PPPP1PPP/R2QK2R w KQkq - 0 2",
+                           "rnbqkbnr/pppp1ppp/8/4p3/2B1P2Q/2N1BN2/PPPP1PPP/R3K2R w KQkq - 0 2");
+    assert!(undeveloped - developed >= 30, "{} vs {}", undeveloped, developed);
+}
+
+#[test]
+fn bishop_pair_grows_as_pawns_come_off() {
+    let closed = Board::from_fen("2b1kb2/pppppppp/8/8/8/8/PPPPPPPP/2B1KB2 w - - 0 1");
+    let open   = Board::from_fen("2b1kb2/pp3ppp/8/8/8/8/PP3PPP/2B1KB2 w - - 0 1");
+    assert!(bishop_pair(&open, Color::White) > bishop_pair(&closed, Color::White));
+    assert!(bishop_pair(&closed, Color::White) > 0);
+    // A single bishop gets nothing however open the board
+    let single = Board::from_fen("2b1k3/8/8/8/8/8/8/2B1K3 w - - 0 1");
+    assert_eq!(bishop_pair(&single, Color::White), 0);
+}
+
+#[test]
+fn endgame_rook_near_the_king_scores_higher() {
+    // Same rank, same open file count: only the distance to the king differs
+    let near = Board::from_fen("7k/8/6R1/8/8/8/8/K7 b - - 0 1");
+    let far  = Board::from_fen("7k/8/1R6/8/8/8/8/K7 b - - 0 1");
+    assert!(evaluate_white(&near) > evaluate_white(&far),
+        "near {} far {}", evaluate_white(&near), evaluate_white(&far));
+}
+
+#[test]
+fn default_weights_reproduce_the_evaluation() {
+    for fen in [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "6k1/5ppp/8/8/8/8/1P6/6K1 w - - 0 1",
+        "7k/8/8/7P/8/8/4B3/6K1 w - - 0 1", // wrong-bishop fortress
+    ] {
+        let board = Board::from_fen(fen);
+        let features = eval_features(&board);
+        assert_eq!(features.dot(&EvalWeights::default()), evaluate_white(&board), "{}", fen);
+        let sided = if board.side == Color::White { evaluate_white(&board) } else { -evaluate_white(&board) };
+        assert_eq!(evaluate(&board), sided + TEMPO, "{}", fen);
+    }
+
+    // A weight change moves the score by exactly feature x change
+    let board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+    let features = eval_features(&board);
+    let weights = EvalWeights { mobility: 5, ..EvalWeights::default() };
+    assert_eq!(features.dot(&weights) - evaluate_white(&board), features.mobility * 2);
+    assert_eq!(features.material, [0, 0, 0, 0, 0]);
+}
+
+#[test]
+fn null_move_hands_over_the_tempo() {
+    for fen in [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 b - - 0 10",
+        "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2",
+    ] {
+        let mut board = Board::from_fen(fen);
+        let before = evaluate(&board);
+        board.make_null_move();
+        // Same position from the other side, and the tempo changes hands
+        assert_eq!(evaluate(&board), -before + 2 * TEMPO, "{}", fen);
+        board.unmake_null_move();
+        assert_eq!(evaluate(&board), before, "{}", fen);
+    }
+}
+
+#[test]
+fn evaluate_white_is_white_relative() {
+    // A symmetric position scores 0 for White whoever is to move; only the
+    // side-relative score carries the tempo
+    for side in ["w", "b"] {
+        let board = Board::from_fen(&format!("r1bqkb1r/pppp1ppp/2n2n2/4p3/4P3/2N2N2/PPPP1PPP/R1BQKB1R {} KQkq - 4 4", side));
+        assert_eq!(evaluate_white(&board), 0);
+        assert_eq!(evaluate(&board), TEMPO);
+    }
+
+    // The colour-swapped position negates it, with either side to move
+    for fen in [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 0 1",
+        "4k3/8/8/8/8/8/8/3QK3 w - - 0 1",
+    ] {
+        let board = Board::from_fen(fen);
+        let white = evaluate_white(&board);
+        assert_ne!(white, 0, "{}", fen);
+        assert_eq!(evaluate_white(&board.mirror()), -white, "{}", fen);
+    }
+}
+
+#[test]
+fn pawn_shield_prefers_intact_to_pushed_to_missing() {
+    // White castled short; only the pawns in front of the king differ
+    let king_safety = |fen: &str| eval_features(&Board::from_fen(fen)).king_safety;
+    let intact  = king_safety("rnbq1rk1/pppppppp/8/8/8/8/PPPPPPPP/RNBQ1RK1 w - - 0 1");
+    let h3      = king_safety("rnbq1rk1/pppppppp/8/8/8/7P/PPPPPPP1/RNBQ1RK1 w - - 0 1");
+    let h4      = king_safety("rnbq1rk1/pppppppp/8/8/7P/8/PPPPPPP1/RNBQ1RK1 w - - 0 1");
+    let no_g    = king_safety("rnbq1rk1/pppppppp/8/8/8/8/PPPPPP1P/RNBQ1RK1 w - - 0 1");
+    assert!(intact > h3 && h3 > h4, "{} {} {}", intact, h3, h4);
+    // A missing pawn also opens the file in front of the king
+    assert!(h4 > no_g, "{} vs {}", h4, no_g);
+
+    // The same holes matter less once the heavy pieces are off
+    let light_intact = king_safety("rnb2rk1/pppppppp/8/8/8/8/PPPPPPPP/RNB2RK1 w - - 0 1");
+    let light_h3     = king_safety("rnb2rk1/pppppppp/8/8/8/7P/PPPPPPP1/RNB2RK1 w - - 0 1");
+    assert!(intact - h3 > light_intact - light_h3, "{} vs {}", intact - h3, light_intact - light_h3);
+
+    // The shield is read from each side's own back rank
+    let black_h6 = king_safety("rnbq1rk1/ppppppp1/7p/8/8/8/PPPPPPPP/RNBQ1RK1 w - - 0 1");
+    assert_eq!(black_h6 - intact, intact - h3);
+}
@@ -0,0 +1,40 @@
+// eval_regression.rs — Pinned static evaluations, so eval changes are deliberate
+//
+// After an intended eval change, regenerate the table with
+//     EVAL_REGEN=1 cargo test --test eval_regression -- --nocapture
+// and paste the printed lines over EXPECTED.
+
+use chess_engine::board::Board;
+use chess_engine::eval::evaluate_white;
+
+// (fen, White-relative evaluation in centipawns)
+const EXPECTED: &[(&str, i32)] = &[
+    ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 0),
+    ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", 101),
+    ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", -54),
+    ("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", 107),
+    ("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", 35),
+    ("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10", 0),
+    ("6k1/5ppp/8/8/8/8/1P6/6K1 w - - 0 1", -271),
+    ("4k3/8/8/8/8/8/8/3QK3 w - - 0 1", 1001),
+    ("4k3/pp3p1p/8/8/8/2P5/P1P2PPP/4K3 w - - 0 1", 195),
+    ("2b1kb2/8/8/8/8/8/8/1N2K1N1 w - - 0 1", -176),
+];
+
+#[test]
+fn evaluations_match_pinned_scores() {
+    if std::env::var_os("EVAL_REGEN").is_some() {
+        for (fen, _) in EXPECTED {
+            println!("    ({:?}, {}),", fen, evaluate_white(&Board::from_fen(fen)));
+        }
+        return;
+    }
+    let mut changed = Vec::new();
+    for &(fen, expected) in EXPECTED {
+        let score = evaluate_white(&Board::from_fen(fen));
+        if score != expected {
+            changed.push(format!("{}: expected {}, got {}", fen, expected, score));
+        }
+    }
+    assert!(changed.is_empty(), "eval changed (EVAL_REGEN=1 to re-pin):\n{}", changed.join("\n"));
+}
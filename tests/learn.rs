@@ -0,0 +1,72 @@
+// learn.rs — Learning table: recording game results and steering away from losses
+
+use chess_engine::board::{Board, Color};
+use chess_engine::learn::{engine_positions, game_outcome, Learning, Outcome, MAX_ENTRIES};
+use chess_engine::search::SearchEngine;
+
+#[test]
+fn recorded_loss_steers_away_from_the_move() {
+    let mut board = Board::start_pos();
+    let mut engine = SearchEngine::new();
+    engine.deterministic = true;
+    let (first, _) = engine.search(&mut board, 3, 60_000);
+
+    // The game after that move went badly
+    let mut game = board.clone();
+    game.make_move(first);
+    let positions = engine_positions(&game, Color::White);
+    assert_eq!(positions, vec![game.hash]);
+    engine.learning.record(&positions, Outcome::Loss);
+
+    let (second, score) = engine.search(&mut board, 3, 60_000);
+    assert_ne!(second, first);
+    // Only the pick is steered: the score reported is the move's searched one
+    let searched = engine.root_scores().iter().find(|(m, _)| *m == second).unwrap().1;
+    assert_eq!(score, searched);
+}
+
+#[test]
+fn learning_file_round_trip_and_cap() {
+    let path = std::env::temp_dir().join(format!("abhin-learn-{}.txt", std::process::id()));
+    let path = path.to_str().unwrap();
+    let _ = std::fs::remove_file(path);
+
+    let mut learning = Learning::new();
+    learning.set_file(path).unwrap();
+    assert!(learning.enabled() && learning.is_empty());
+    learning.record(&[1, 2], Outcome::Loss);
+    learning.record(&[2], Outcome::Win);
+    learning.save().unwrap();
+
+    let mut loaded = Learning::new();
+    loaded.set_file(path).unwrap();
+    assert_eq!((loaded.nudge(1), loaded.nudge(2), loaded.nudge(3)), (learning.nudge(1), 0, 0));
+    assert!(loaded.nudge(1) < 0);
+
+    // Bounded: the oldest entries give way
+    let many: Vec<u64> = (10..10 + MAX_ENTRIES as u64).collect();
+    loaded.record(&many, Outcome::Draw);
+    loaded.record(&[5], Outcome::Win);
+    assert!(loaded.len() <= MAX_ENTRIES);
+    assert!(loaded.nudge(5) > 0);
+
+    loaded.set_file("").unwrap();
+    assert!(!loaded.enabled());
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn outcome_of_a_finished_game() {
+    // Fool's mate: White is mated
+    let mated = Board::from_fen("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3");
+    assert_eq!(game_outcome(&mated, Color::White, 0), Some(Outcome::Loss));
+    assert_eq!(game_outcome(&mated, Color::Black, 0), Some(Outcome::Win));
+
+    let stalemate = Board::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1");
+    assert_eq!(game_outcome(&stalemate, Color::White, 900), Some(Outcome::Draw));
+
+    // Unfinished: only a decisive last score counts
+    let start = Board::start_pos();
+    assert_eq!(game_outcome(&start, Color::White, 30), None);
+    assert_eq!(game_outcome(&start, Color::White, -600), Some(Outcome::Loss));
+}
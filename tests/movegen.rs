@@ -0,0 +1,91 @@
+// movegen.rs — Legal move generation edge cases
+
+use chess_engine::board::Board;
+use chess_engine::movegen::{count_legal_moves, generate_moves, generate_moves_sorted, legal_moves, perft};
+
+// The perft suite's positions (see perft.rs)
+const PERFT_FENS: [&str; 6] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+];
+
+// Each perft position and every position one move on
+fn perft_positions() -> Vec<Board> {
+    let mut boards = Vec::new();
+    for fen in PERFT_FENS {
+        let board = Board::from_fen(fen);
+        for mv in generate_moves(&board) {
+            let mut child = board.clone();
+            child.make_move(mv);
+            boards.push(child);
+        }
+        boards.push(board);
+    }
+    boards
+}
+
+fn has_move(board: &Board, uci: &str) -> bool {
+    generate_moves(board).iter().any(|m| m.to_uci() == uci)
+}
+
+#[test]
+fn en_passant_exposing_king_on_rank_is_illegal() {
+    // bxc6 e.p. would clear b5 and c5, opening the rook on h5 onto a5
+    let white = Board::from_fen("8/8/8/KPp4r/8/8/8/4k3 w - c6 0 1");
+    assert!(!has_move(&white, "b5c6"));
+    assert_eq!(count_legal_moves(&white), generate_moves(&white).len());
+
+    // Mirror for Black: fxg3 e.p. opens the a4 rook onto h4
+    let black = Board::from_fen("4K3/8/8/8/R4pPk/8/8/8 b - g3 0 1");
+    assert!(!has_move(&black, "f4g3"));
+    assert_eq!(count_legal_moves(&black), generate_moves(&black).len());
+
+    // Without the rook the same capture is fine
+    let free = Board::from_fen("8/8/8/KPp5/8/8/8/4k3 w - c6 0 1");
+    assert!(has_move(&free, "b5c6"));
+    let mut free = free;
+    assert_eq!(perft(&mut free, 1), generate_moves(&free).len() as u64);
+}
+
+#[test]
+fn sorted_moves_are_canonical_and_complete() {
+    let board = Board::from_fen("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1");
+    let sorted = generate_moves_sorted(&board);
+
+    let mut plain: Vec<String> = generate_moves(&board).iter().map(|m| m.to_uci()).collect();
+    let mut listed: Vec<String> = sorted.iter().map(|m| m.to_uci()).collect();
+    plain.sort();
+    listed.sort();
+    assert_eq!(plain, listed);
+
+    let keys: Vec<(u8, u8)> = sorted.iter().map(|m| (m.from, m.to)).collect();
+    assert!(keys.windows(2).all(|w| w[0] <= w[1]), "not sorted");
+    assert_eq!(sorted, generate_moves_sorted(&board));
+
+    // Promotions: capture (lower target square) first, each knight to queen
+    let board = Board::from_fen("r3k3/1P6/8/8/8/8/8/4K3 w - - 0 1");
+    let promos: Vec<String> = generate_moves_sorted(&board).iter()
+        .filter(|m| m.promotion.is_some()).map(|m| m.to_uci()).collect();
+    assert_eq!(promos, ["b7a8n", "b7a8b", "b7a8r", "b7a8q", "b7b8n", "b7b8b", "b7b8r", "b7b8q"]);
+}
+
+#[test]
+fn lazy_iterator_yields_the_generated_moves() {
+    for board in perft_positions() {
+        let lazy: Vec<_> = legal_moves(&board).collect();
+        assert_eq!(lazy, generate_moves(&board), "{}", board.hash);
+        // Stopping early is just a prefix
+        assert_eq!(legal_moves(&board).take(3).collect::<Vec<_>>(), lazy[..lazy.len().min(3)]);
+    }
+}
+
+#[test]
+fn count_matches_generated_moves() {
+    for board in perft_positions() {
+        assert_eq!(count_legal_moves(&board), generate_moves(&board).len(), "{}", board.hash);
+    }
+}
@@ -0,0 +1,49 @@
+// movegen_alloc.rs — Legal move generation must not touch the heap
+//
+// A counting global allocator sees every allocation in this test binary,
+// so it holds a single test.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use chess_engine::board::Board;
+use chess_engine::movegen::{count_legal_moves, generate_moves_into, has_legal_move, MoveList};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+#[test]
+fn legality_checks_do_not_allocate() {
+    let mut board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+    // Some history, which cloning the board used to copy for every move
+    for mv in ["e1g1", "e8c8", "a2a4", "b4a3"] {
+        assert!(board.make_uci_move(mv));
+    }
+    let mut moves = MoveList::new();
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    generate_moves_into(&board, &mut moves);
+    let legal = count_legal_moves(&board);
+    let any = has_legal_move(&board);
+    let lazy = board.legal_moves().count();
+    let after = ALLOCATIONS.load(Ordering::Relaxed);
+
+    assert_eq!(after - before, 0, "legal move generation allocated");
+    assert_eq!(moves.len(), legal);
+    assert_eq!(lazy, legal);
+    assert!(any && legal > 40);
+}
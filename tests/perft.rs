@@ -0,0 +1,92 @@
+// perft.rs — Move generator regression suite on the standard perft positions
+//
+// Depths are capped so the whole suite stays within a few seconds on a
+// debug build. On a mismatch the per-move breakdown is printed so the
+// offending subtree can be compared against a reference engine.
+
+use chess_engine::board::Board;
+use chess_engine::movegen::{perft, perft_divide, perft_hash};
+
+const POSITIONS: &[(&str, &str, &[u64])] = &[
+    ("startpos",
+     "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+     &[20, 400, 8902, 197281]),
+    ("kiwipete",
+     "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+     &[48, 2039, 97862]),
+    ("position 3",
+     "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+     &[14, 191, 2812]),
+    ("position 4",
+     "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+     &[6, 264, 9467]),
+    ("position 5",
+     "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+     &[44, 1486, 62379]),
+    ("position 6",
+     "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+     &[46, 2079, 89890]),
+];
+
+fn check(name: &str, fen: &str, expected: &[u64]) {
+    let mut board = Board::from_fen(fen);
+    for (i, &want) in expected.iter().enumerate() {
+        let depth = i as u8 + 1;
+        let got = perft(&mut board, depth);
+        if got != want {
+            for (mv, n) in perft_divide(&mut board, depth) {
+                eprintln!("  {}: {}", mv.to_uci(), n);
+            }
+            panic!("{}: perft({}) = {}, expected {}", name, depth, got, want);
+        }
+    }
+}
+
+#[test]
+fn perft_startpos() {
+    let (name, fen, counts) = POSITIONS[0];
+    check(name, fen, counts);
+}
+
+#[test]
+fn perft_kiwipete() {
+    let (name, fen, counts) = POSITIONS[1];
+    check(name, fen, counts);
+}
+
+#[test]
+fn perft_position_3() {
+    let (name, fen, counts) = POSITIONS[2];
+    check(name, fen, counts);
+}
+
+#[test]
+fn perft_position_4() {
+    let (name, fen, counts) = POSITIONS[3];
+    check(name, fen, counts);
+}
+
+#[test]
+fn perft_position_5() {
+    let (name, fen, counts) = POSITIONS[4];
+    check(name, fen, counts);
+}
+
+#[test]
+fn perft_position_6() {
+    let (name, fen, counts) = POSITIONS[5];
+    check(name, fen, counts);
+}
+
+#[test]
+fn perft_hash_startpos() {
+    let mut board = Board::start_pos();
+    assert_eq!(perft_hash(&mut board, 4), (197281, 0));
+}
+
+#[test]
+fn perft_hash_kiwipete() {
+    // Castling, en passant and promotions all re-key the hash
+    let mut board = Board::from_fen(POSITIONS[1].1);
+    assert_eq!(perft_hash(&mut board, 3), (97862, 0));
+}
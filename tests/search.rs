@@ -0,0 +1,663 @@
+// search.rs — Search behaviour through the public SearchEngine API
+
+use chess_engine::board::Board;
+use chess_engine::movegen::generate_moves;
+use chess_engine::search::SearchEngine;
+
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+#[test]
+fn pruning_switches_off() {
+    let mut board = Board::from_fen(KIWIPETE);
+
+    let mut pruned = SearchEngine::new();
+    let (_, _) = pruned.search(&mut board, 4, 60_000);
+
+    let mut full = SearchEngine::new();
+    full.use_nullmove = false;
+    full.use_lmr = false;
+    full.use_futility = false;
+    let (mv, _) = full.search(&mut board, 4, 60_000);
+
+    assert!(generate_moves(&board).contains(&mv), "illegal move {}", mv.to_uci());
+    assert!(full.last_nodes() > pruned.last_nodes(),
+        "unpruned {} vs pruned {}", full.last_nodes(), pruned.last_nodes());
+}
+
+#[test]
+fn futility_prunes_in_the_main_search() {
+    // Only the futility switch differs. Delta pruning in qsearch alone
+    // barely moves the count in this quiet opening; pruning quiet moves
+    // at depths 1 and 2 as well saves a good quarter of the nodes.
+    let nodes = |futility: bool| {
+        let mut board = Board::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3");
+        let mut engine = SearchEngine::new();
+        engine.deterministic = true;
+        engine.use_nullmove = false;
+        engine.use_lmr = false;
+        engine.use_futility = futility;
+        engine.search(&mut board, 5, 60_000);
+        engine.last_nodes()
+    };
+    let (on, off) = (nodes(true), nodes(false));
+    assert!(on * 4 < off * 3, "futility {} vs none {}", on, off);
+}
+
+#[test]
+fn score_is_from_root_mover_perspective() {
+    // Black is a queen up; either side to move, Black should be winning
+    let fen = "4k3/8/8/3q4/8/8/8/4K3";
+    let mut engine = SearchEngine::new();
+
+    let mut black = Board::from_fen(&format!("{} b - - 0 1", fen));
+    let (_, score) = engine.search(&mut black, 3, 60_000);
+    assert!(score > 500, "black to move scored {}", score);
+
+    engine.clear();
+    let mut white = Board::from_fen(&format!("{} w - - 0 1", fen));
+    let (_, score) = engine.search(&mut white, 3, 60_000);
+    assert!(score < -500, "white to move scored {}", score);
+}
+
+#[test]
+fn contempt_only_at_the_root() {
+    let mut board = Board::start_pos();
+    let mut engine = SearchEngine::new();
+    engine.contempt = 25;
+
+    // Every root move repeats a position already seen twice
+    for mv in generate_moves(&board) {
+        board.make_move(mv);
+        engine.push_position(&board);
+        engine.push_position(&board);
+        board.unmake_move();
+    }
+    let (_, score) = engine.search(&mut board, 3, 60_000);
+    assert_eq!(score, -25);
+
+    // Deeper in the tree a draw is just a draw
+    for ply in 2..10 {
+        assert_eq!(engine.draw_score(ply), 0);
+    }
+}
+
+#[test]
+fn stalemate_carries_no_contempt() {
+    // Qf7 stalemates straight from the root
+    let mut board = Board::from_fen("7k/8/6K1/8/8/8/8/5Q2 w - - 0 1");
+    let mut engine = SearchEngine::new();
+    engine.contempt = 25;
+    engine.multi_pv = 64; // exact scores for every root move
+    engine.search(&mut board, 2, 60_000);
+    let stalemate = engine.root_scores().iter().find(|(m, _)| m.to_uci() == "f1f7").unwrap().1;
+    assert_eq!(stalemate, 0);
+}
+
+#[test]
+fn root_order_leads_with_best_move() {
+    // Rxd5 wins the queen outright
+    let mut board = Board::from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1");
+    let mut engine = SearchEngine::new();
+    let (best, score) = engine.search(&mut board, 5, 60_000);
+    assert_eq!(best.to_uci(), "d2d5");
+
+    // The final iteration searched the best move first, and it still tops
+    // the scores of every root move
+    let scores = engine.root_scores();
+    assert_eq!(scores.len(), generate_moves(&board).len());
+    assert_eq!(scores[0].0, best);
+    assert_eq!(scores[0].1, score);
+    assert!(scores.iter().all(|&(_, s)| s <= score));
+}
+
+#[test]
+fn prepass_orders_the_first_iteration() {
+    // Rxa7 wins a pawn by SEE but leaves the queen to the e5 pawn, and
+    // every queen capture is recaptured: a queen retreat should lead
+    let mut board = Board::from_fen("6k1/p3p3/3p4/2p1p3/3Q4/8/8/R5K1 w - - 0 1");
+    let mut engine = SearchEngine::new();
+    engine.deterministic = true;
+    engine.search(&mut board, 1, 60_000);
+    let first = engine.root_scores()[0].0;
+    assert!(first.to_uci().starts_with("d4") && first.captured.is_none(), "{} first", first.to_uci());
+}
+
+#[test]
+fn fail_low_extends_soft_limit() {
+    use chess_engine::search::is_fail_low;
+
+    // A deeper iteration dropping from +80 to -40 is a fail-low; small
+    // wobbles and improvements are not
+    assert!(is_fail_low(80, -40));
+    assert!(!is_fail_low(80, 60));
+    assert!(!is_fail_low(80, 200));
+
+    let mut board = Board::start_pos();
+    let mut engine = SearchEngine::new();
+    engine.search(&mut board, 1, 1000);
+    let normal = engine.soft_limit(0, false, false);
+    let extended = engine.soft_limit(0, false, true);
+    assert!(extended > normal, "{} vs {}", extended, normal);
+    assert!(extended >= engine.soft_limit(0, true, false));
+    assert_eq!(extended, 1000);
+}
+
+#[test]
+fn qsearch_sees_quiet_promotion() {
+    // After any White king move Black queens on a1 and nothing can take it;
+    // at depth 1 only quiescence can find that
+    let mut board = Board::from_fen("4k3/8/8/8/8/8/p7/4K3 w - - 0 1");
+    let mut engine = SearchEngine::new();
+    let (_, score) = engine.search(&mut board, 1, 60_000);
+    assert!(score < -500, "promotion missed: {}", score);
+}
+
+#[test]
+fn stop_during_first_iteration_keeps_best_searched() {
+    // A zero time limit stops at the first clock check, part-way through
+    // depth 1 in this position
+    let mut board = Board::from_fen(KIWIPETE);
+    let mut engine = SearchEngine::new();
+    let (mv, score) = engine.search(&mut board, 1, 0);
+
+    let searched = engine.root_scores();
+    assert!(!searched.is_empty());
+    assert!(searched.len() < generate_moves(&board).len(), "depth 1 was not interrupted");
+
+    // The best of the moves searched so far, whichever position it had;
+    // later moves that merely tie it (fail-low at alpha) do not replace it
+    let best_score = searched.iter().map(|&(_, s)| s).max().unwrap();
+    let best = searched.iter().find(|&&(_, s)| s == best_score).unwrap().0;
+    assert_eq!((mv, score), (best, best_score));
+}
+
+#[test]
+fn killers_recorded_up_to_max_ply() {
+    use chess_engine::search::MAX_PLY;
+
+    let board = Board::start_pos();
+    let a = board.parse_uci("g1f3").unwrap();
+    let b = board.parse_uci("b1c3").unwrap();
+    let mut engine = SearchEngine::new();
+
+    engine.store_killer(MAX_PLY - 1, a);
+    engine.store_killer(MAX_PLY - 1, b);
+    engine.store_killer(MAX_PLY - 1, b); // a repeat does not push `a` out
+    assert_eq!(engine.killers(MAX_PLY - 1), [Some(b), Some(a)]);
+
+    // Past the table: ignored, no panic
+    engine.store_killer(MAX_PLY, a);
+    engine.store_killer(MAX_PLY + 50, a);
+    assert_eq!(engine.killers(MAX_PLY + 50), [None, None]);
+}
+
+#[test]
+fn aspiration_window_width() {
+    let search = |window: i32| {
+        let mut board = Board::from_fen(KIWIPETE);
+        let mut engine = SearchEngine::new();
+        engine.deterministic = true;
+        engine.aspiration_window = window;
+        let (mv, score) = engine.search(&mut board, 5, 60_000);
+        (mv, score, engine.aspiration_researches)
+    };
+
+    // Full window (0) and one far wider than any score swing agree, and
+    // neither needs a re-search
+    let (full_mv, full_score, full_re) = search(0);
+    let (wide_mv, wide_score, wide_re) = search(100_000);
+    assert_eq!((full_mv, full_score), (wide_mv, wide_score));
+    assert_eq!((full_re, wide_re), (0, 0));
+
+    // A 1cp window fails on almost every aspirated iteration
+    let (_, _, default_re) = search(35);
+    let (narrow_mv, _, narrow_re) = search(1);
+    assert!(narrow_re > default_re, "narrow {} vs default {}", narrow_re, default_re);
+    let board = Board::from_fen(KIWIPETE);
+    assert!(generate_moves(&board).contains(&narrow_mv));
+}
+
+#[test]
+fn set_position_then_go() {
+    let mut board = Board::from_fen(KIWIPETE);
+    assert!(board.make_uci_move("e2a6"));
+
+    let mut engine = SearchEngine::new();
+    engine.set_position(&board);
+    let (mv, _) = engine.go(3, 60_000);
+    assert!(generate_moves(&board).contains(&mv), "illegal move {}", mv.to_uci());
+    // Black to move after Bxa6, so the answer must be a Black move
+    assert_eq!(board.squares[mv.from as usize].map(|cp| cp.color), Some(board.side));
+
+    // Positions pushed for another game are forgotten: from a fresh start
+    // position, no reply repeats anything
+    let mut start = Board::start_pos();
+    for mv in generate_moves(&start) {
+        start.make_move(mv);
+        engine.push_position(&start);
+        engine.push_position(&start);
+        start.unmake_move();
+    }
+    engine.contempt = 25;
+    engine.set_position(&start);
+    engine.go(3, 60_000);
+    assert!(engine.root_scores().iter().all(|&(_, s)| s != -25), "{:?}", engine.root_scores());
+}
+
+#[test]
+fn wdl_model_shape() {
+    use chess_engine::search::wdl;
+
+    let (w, d, l) = wdl(0);
+    assert_eq!(w, l);
+    assert!(d > 700, "even score should be mostly drawn: {} {} {}", w, d, l);
+
+    let (w, d, l) = wdl(600);
+    assert!(w > 950, "{} {} {}", w, d, l);
+    assert_eq!(wdl(-600), (l, d, w));
+    for score in [-100_000, -300, -1, 1, 75, 100_000] {
+        let (w, d, l) = wdl(score);
+        assert_eq!(w + d + l, 1000, "{}", score);
+    }
+    assert!(wdl(100).0 < wdl(200).0);
+}
+
+#[test]
+fn qsearch_looks_past_stand_pat_at_pending_promotion() {
+    use chess_engine::eval::evaluate;
+
+    // Black must stop b8=Q; only Rh8 covers the square. Any other move
+    // leaves White to stand pat with a pawn that is really a queen.
+    let mut board = Board::from_fen("8/1P6/6k1/8/8/KP6/P7/7r b - - 0 1");
+    let mut engine = SearchEngine::new();
+    let (mv, score) = engine.search(&mut board, 1, 60_000);
+    assert_eq!(mv.to_uci(), "h1h8");
+    assert!(score > 200, "{}", score);
+
+    // After a move that ignores the pawn, the static eval still looks fine
+    // for Black, but the search does not
+    assert!(board.make_uci_move("h1h2"));
+    let stand_pat = -evaluate(&board);
+    let (_, white_score) = engine.search(&mut board, 1, 60_000);
+    assert!(-white_score < stand_pat - 300, "stand pat {} vs searched {}", stand_pat, -white_score);
+}
+
+#[test]
+fn skill_level_varies_play() {
+    use chess_engine::search::MAX_SKILL;
+    use std::collections::HashSet;
+
+    let first_move = |skill: u8, seed: u64| {
+        let mut board = Board::start_pos();
+        let mut engine = SearchEngine::new();
+        engine.deterministic = true;
+        engine.skill_level = skill;
+        engine.set_skill_seed(seed);
+        let (mv, _) = engine.search(&mut board, 3, 60_000);
+        assert!(generate_moves(&board).contains(&mv), "illegal move {}", mv.to_uci());
+        mv.to_uci()
+    };
+
+    let weak: HashSet<String> = (1..=8).map(|seed| first_move(0, seed)).collect();
+    assert!(weak.len() > 2, "skill 0 played only {:?}", weak);
+    let full: HashSet<String> = (1..=4).map(|seed| first_move(MAX_SKILL, seed)).collect();
+    assert_eq!(full.len(), 1, "max skill varied: {:?}", full);
+}
+
+#[test]
+fn low_skill_sometimes_misses_a_tactic() {
+    use chess_engine::search::MAX_SKILL;
+
+    // The knight on d5 hangs to the rook
+    let fen = "4k3/8/8/3n4/8/8/8/3RK3 w - - 0 1";
+    let play = |skill: u8, seed: u64| {
+        let mut board = Board::from_fen(fen);
+        let mut engine = SearchEngine::new();
+        engine.deterministic = true;
+        engine.skill_level = skill;
+        engine.set_skill_seed(seed);
+        engine.search(&mut board, 4, 60_000).0.to_uci()
+    };
+
+    let misses = (1..=20).filter(|&seed| play(0, seed) != "d1d5").count();
+    assert!(misses > 0, "skill 0 never missed Rxd5");
+    assert!(misses < 20, "skill 0 never found Rxd5");
+    for seed in 1..=5 {
+        assert_eq!(play(MAX_SKILL, seed), "d1d5");
+    }
+}
+
+#[test]
+fn game_history_counts_towards_threefold() {
+    // The start position has occurred twice; Black's Ng8 makes it three times
+    let mut board = Board::start_pos();
+    for mv in "g1f3 g8f6 f3g1 f6g8 g1f3 g8f6 f3g1".split_whitespace() {
+        assert!(board.make_uci_move(mv));
+    }
+    let mut engine = SearchEngine::new();
+    engine.deterministic = true;
+    engine.contempt = 0;
+    engine.search(&mut board, 3, 60_000);
+    let back = engine.root_scores().iter()
+        .find(|(m, _)| m.to_uci() == "f6g8").expect("f6g8 searched").1;
+    assert_eq!(back, 0);
+
+    // The same position without the game behind it: Ng8 is no draw
+    let mut fresh = board.clone();
+    let mut again = Board::from_fen("rnbqkb1r/pppppppp/5n2/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 7 4");
+    assert_eq!(again.hash, fresh.hash);
+    engine.search(&mut again, 3, 60_000);
+    let back = engine.root_scores().iter()
+        .find(|(m, _)| m.to_uci() == "f6g8").expect("f6g8 searched").1;
+    assert_ne!(back, 0);
+    assert!(fresh.make_uci_move("f6g8") && fresh.is_repetition());
+}
+
+#[test]
+fn perpetual_check_converges_to_a_draw() {
+    // Two rooks' worth down, White checks on e8 and h5 forever; the cycle
+    // has been through twice already, so the next lap is threefold
+    let mut board = Board::from_fen("6k1/6p1/8/8/8/8/qr2QPPP/6K1 w - - 0 1");
+    for mv in "e2e8 g8h7 e8h5 h7g8 h5e8 g8h7 e8h5 h7g8".split_whitespace() {
+        assert!(board.make_uci_move(mv));
+    }
+    let mut engine = SearchEngine::new();
+    engine.deterministic = true;
+    engine.contempt = 0;
+    for depth in [4, 6, 8] {
+        let (mv, score) = engine.search(&mut board, depth, 60_000);
+        assert_eq!((mv.to_uci().as_str(), score), ("h5e8", 0), "depth {}", depth);
+        assert!(engine.last_nodes() < 50_000, "{} nodes at depth {}", engine.last_nodes(), depth);
+    }
+}
+
+#[test]
+fn variety_spreads_early_moves() {
+    use std::collections::HashSet;
+
+    let first_move = |variety: i32, seed: u64| {
+        let mut board = Board::start_pos();
+        let mut engine = SearchEngine::new();
+        engine.deterministic = true;
+        engine.variety = variety;
+        engine.set_skill_seed(seed);
+        let (mv, _) = engine.search(&mut board, 3, 60_000);
+        assert!(generate_moves(&board).contains(&mv), "illegal move {}", mv.to_uci());
+        mv.to_uci()
+    };
+
+    let varied: HashSet<String> = (1..=8).map(|seed| first_move(30, seed)).collect();
+    assert!(varied.len() > 1, "variety played only {:?}", varied);
+    let plain: HashSet<String> = (1..=4).map(|seed| first_move(0, seed)).collect();
+    assert_eq!(plain.len(), 1);
+
+    // Past the threshold the best move is played
+    let mut board = Board::start_pos();
+    assert!(board.make_uci_move("g1f3") && board.make_uci_move("g8f6"));
+    let mut engine = SearchEngine::new();
+    engine.deterministic = true;
+    engine.variety = 100;
+    engine.variety_moves = 1;
+    let expected = {
+        let mut plain = SearchEngine::new();
+        plain.deterministic = true;
+        plain.search(&mut board.clone(), 3, 60_000).0
+    };
+    for seed in 1..=4 {
+        engine.set_skill_seed(seed);
+        assert_eq!(engine.search(&mut board, 3, 60_000).0, expected);
+    }
+
+    // A FEN's move number counts as well: the start position given as
+    // move 30 has no history, but is no opening any more
+    let mut late = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 30");
+    let expected = {
+        let mut plain = SearchEngine::new();
+        plain.deterministic = true;
+        plain.search(&mut late.clone(), 3, 60_000).0
+    };
+    engine.variety_moves = 10;
+    for seed in 1..=4 {
+        engine.set_skill_seed(seed);
+        assert_eq!(engine.search(&mut late, 3, 60_000).0, expected);
+    }
+}
+
+#[test]
+fn tt_best_move_matches_search() {
+    let mut board = Board::from_fen(KIWIPETE);
+    let mut engine = SearchEngine::new();
+    assert_eq!(engine.tt_best_move(&board), None);
+
+    let (mv, _) = engine.search(&mut board, 4, 60_000);
+    assert_eq!(engine.tt_best_move(&board), Some(mv));
+
+    // A position the search never reached has nothing stored
+    let other = Board::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1");
+    assert_eq!(engine.tt_best_move(&other), None);
+}
+
+#[test]
+fn shallow_tt_move_is_searched_first() {
+    // A depth-1 entry cannot cut a deeper search, but its move still leads
+    // the ordering: the interrupted iteration has searched it first
+    let mut board = Board::from_fen(KIWIPETE);
+    let mut engine = SearchEngine::new();
+    let hint = generate_moves(&board).into_iter().find(|m| m.to_uci() == "a2a3").unwrap();
+    engine.tt.store(board.hash, 1, 0, 2, hint);
+    engine.search(&mut board, 1, 0);
+    assert_eq!(engine.root_scores()[0].0, hint);
+}
+
+#[test]
+fn pv_is_legal_and_starts_with_best_move() {
+    let mut engine = SearchEngine::new();
+    assert!(engine.pv().is_empty());
+
+    let mut board = Board::from_fen(KIWIPETE);
+    let (best, _) = engine.search(&mut board, 5, 60_000);
+    let pv = engine.pv();
+    assert!(pv.len() >= 2, "{:?}", pv);
+    assert_eq!(pv[0], best);
+    let mut line = board.clone();
+    for mv in pv {
+        assert!(generate_moves(&line).contains(&mv), "{} illegal", mv.to_uci());
+        line.make_move(mv);
+    }
+
+    // Mate in one: the PV is just the mating move
+    let mut mate = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+    let (mv, _) = engine.search(&mut mate, 3, 60_000);
+    assert_eq!(engine.pv(), vec![mv]);
+}
+
+#[test]
+fn promotions_never_become_killers() {
+    use chess_engine::search::MAX_PLY;
+
+    // Quiet promotions (b8=Q and friends) cut off all over this tree
+    let mut board = Board::from_fen("8/1P4k1/8/8/8/8/6K1/8 w - - 0 1");
+    let mut engine = SearchEngine::new();
+    engine.search(&mut board, 5, 60_000);
+    let killers: Vec<_> = (0..MAX_PLY).flat_map(|ply| engine.killers(ply)).flatten().collect();
+    assert!(!killers.is_empty());
+    assert!(killers.iter().all(|k| k.is_quiet()), "{:?}", killers);
+
+    let promo = board.parse_uci("b7b8q").unwrap();
+    engine.store_killer(0, promo);
+    assert!(!engine.killers(0).contains(&Some(promo)));
+}
+
+#[test]
+fn mate_on_the_hundredth_ply_beats_fifty_moves() {
+    // 99 reversible plies played; Ra8 is both the 100th and mate
+    let mut board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 99 80");
+    let mut engine = SearchEngine::new();
+    let (mv, score) = engine.search(&mut board, 2, 60_000);
+    assert_eq!(mv.to_uci(), "a1a8");
+    assert!(score > 800_000, "mate scored {}", score);
+
+    assert!(board.make_uci_move("a1a8"));
+    assert_eq!(board.halfmove, 100);
+    assert!(!board.is_fifty_move_rule());
+    board.unmake_move();
+    assert!(board.make_uci_move("a1a7"));
+    assert!(board.is_fifty_move_rule());
+}
+
+#[test]
+fn threaded_search_stops_and_joins() {
+    use std::sync::atomic::Ordering;
+    use std::sync::{Arc, Mutex};
+
+    // The way main runs it: the engine behind a lock, searching on its own
+    // thread, told to stop from outside
+    let engine = Arc::new(Mutex::new(SearchEngine::new()));
+    let stop = engine.lock().unwrap().stop_signal.clone();
+    let worker = Arc::clone(&engine);
+    let handle = std::thread::spawn(move || {
+        let mut board = Board::from_fen(KIWIPETE);
+        worker.lock().unwrap().search(&mut board, 64, 600_000).0
+    });
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    stop.store(true, Ordering::Relaxed);
+
+    let mv = handle.join().expect("search thread panicked");
+    assert!(generate_moves(&Board::from_fen(KIWIPETE)).contains(&mv));
+    assert!(!engine.is_poisoned());
+}
+
+#[test]
+fn self_play_never_makes_an_illegal_move() {
+    // In debug builds pvs also asserts this for every move it searches
+    for fen in [
+        KIWIPETE,
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    ] {
+        let mut board = Board::from_fen(fen);
+        let mut engine = SearchEngine::new();
+        engine.deterministic = true;
+        for _ in 0..20 {
+            if generate_moves(&board).is_empty() || board.is_fifty_move_rule() { break; }
+            let (mv, _) = engine.search(&mut board, 2, 0);
+            assert!(generate_moves(&board).contains(&mv), "{} in {}", mv.to_uci(), fen);
+            let mover = board.side;
+            board.make_move(mv);
+            let king = board.find_king(mover).expect("king");
+            assert!(!board.is_attacked(king, board.side), "{} leaves the king in check", mv.to_uci());
+        }
+    }
+}
+
+#[test]
+fn null_move_needs_beta_cleared_after_the_tempo_swing() {
+    use chess_engine::eval::{evaluate, TEMPO};
+    use chess_engine::search::null_move_ok;
+
+    let mut board = Board::from_fen(KIWIPETE);
+    let eval = evaluate(&board);
+    assert!(null_move_ok(&board, eval - 2 * TEMPO));
+    assert!(!null_move_ok(&board, eval - 2 * TEMPO + 1));
+
+    // Never twice in a row, whatever the eval
+    board.make_null_move();
+    assert!(!null_move_ok(&board, -10_000));
+}
+
+#[test]
+fn search_past_max_ply_depth_does_not_panic() {
+    use chess_engine::search::MAX_PLY;
+
+    // Locked pawns, kings only: every iteration is cheap, so the search
+    // runs through depths beyond MAX_PLY with the ply-indexed tables
+    // bounded all the way
+    let mut board = Board::from_fen("4k3/p1p1p1p1/P1P1P1P1/8/8/8/8/4K3 w - - 0 1");
+    let mut engine = SearchEngine::new();
+    let (mv, score) = engine.search(&mut board, MAX_PLY as u8 + 20, 60_000);
+    assert!(generate_moves(&board).contains(&mv));
+    assert!(score.abs() < 100, "{}", score);
+    assert!(engine.pv().len() <= MAX_PLY);
+    // Killers were recorded along the way, past the first few plies
+    assert!((8..MAX_PLY).any(|ply| engine.killers(ply)[0].is_some()));
+}
+
+#[test]
+fn stop_mid_iteration_keeps_a_searched_better_move() {
+    // Depth 4 plays Nc3, depth 5 switches to d4
+    let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+    let run = |depth: u8, node_limit: u64| {
+        let mut board = Board::from_fen(fen);
+        let mut engine = SearchEngine::new();
+        engine.deterministic = true;
+        engine.node_limit = node_limit;
+        let (mv, _) = engine.search(&mut board, depth, 60_000);
+        (mv.to_uci(), engine.last_nodes())
+    };
+    let (shallow, done4) = run(4, 0);
+    let (deep, done5) = run(5, 0);
+    assert_eq!((shallow.as_str(), deep.as_str()), ("b1c3", "d2d4"));
+
+    // Stopped partway through depth 5: d4 is found late in the iteration,
+    // and once fully searched it is the answer even though the iteration
+    // never completes; before that the depth-4 move still is
+    assert_eq!(run(5, done5 - 1).0, "d2d4");
+    assert_eq!(run(5, done4 + 1).0, "b1c3");
+    for n in [done4 + (done5 - done4) / 2, done5 - 500] {
+        let mv = run(5, n).0;
+        assert!(mv == "b1c3" || mv == "d2d4", "{} after {} nodes", mv, n);
+    }
+}
+
+#[test]
+fn qsearch_see_threshold_changes_the_tree() {
+    let nodes = |threshold: i32| {
+        let mut board = Board::from_fen(KIWIPETE);
+        let mut engine = SearchEngine::new();
+        engine.deterministic = true;
+        engine.qsee_threshold = threshold;
+        engine.search(&mut board, 3, 60_000);
+        engine.last_nodes()
+    };
+    // Captures losing up to 150cp searched, the default's -50,
+    // then only captures that win material
+    let (loose, default, winning) = (nodes(-150), nodes(-50), nodes(1));
+    assert!(loose > default && default > winning, "{} / {} / {}", loose, default, winning);
+}
+
+#[test]
+fn stats_accessors_match_the_info_line() {
+    // The info lines go to stdout, so the search runs in a child copy of
+    // this test whose output is read back here
+    const CHILD: &str = "SEARCH_STATS_CHILD";
+    if std::env::var_os(CHILD).is_some() {
+        let mut board = Board::from_fen(KIWIPETE);
+        let mut engine = SearchEngine::new();
+        engine.search(&mut board, 4, 60_000);
+        println!("stats {} {} {}", engine.last_nodes(), engine.last_time_ms(), engine.last_nps());
+        return;
+    }
+    let out = std::process::Command::new(std::env::current_exe().unwrap())
+        .args(["stats_accessors_match_the_info_line", "--exact", "--nocapture", "--test-threads=1"])
+        .env(CHILD, "1")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let field = |line: &str, key: &str| -> u64 {
+        let mut words = line.split_whitespace().skip_while(|&w| w != key).skip(1);
+        words.next().and_then(|v| v.parse().ok()).unwrap_or_else(|| panic!("no {} in {}", key, line))
+    };
+    let info = stdout.lines().rfind(|l| l.starts_with("info depth 4 ")).expect("depth 4 info");
+    let stats: Vec<u64> = stdout.lines().find_map(|l| l.strip_prefix("stats "))
+        .expect("stats line").split(' ').map(|v| v.parse().unwrap()).collect();
+    let (nodes, time, nps) = (stats[0], stats[1], stats[2]);
+
+    // The last depth is the whole search: same nodes, and the clock read
+    // just after it
+    assert!(nodes > 0 && nps > 0);
+    assert_eq!(nodes, field(info, "nodes"), "{}", info);
+    let info_time = field(info, "time");
+    assert!(time >= info_time && time <= info_time + 50, "{} vs {}", time, info);
+    assert_eq!(nps, nodes * 1000 / time.max(1));
+}
@@ -0,0 +1,76 @@
+// tablebase.rs — The search takes tablebase answers through the probe hook
+#![cfg(feature = "syzygy")]
+
+use chess_engine::board::{Board, Color};
+use chess_engine::search::SearchEngine;
+use chess_engine::movegen::generate_moves;
+use chess_engine::tablebase::{piece_total, scan_syzygy_dir, TablebaseProbe, Wdl};
+
+// Claims every three-piece position is won for White
+struct WhiteWins;
+
+impl TablebaseProbe for WhiteWins {
+    fn max_pieces(&self) -> u32 { 3 }
+    fn probe_wdl(&self, board: &Board) -> Option<Wdl> {
+        Some(if board.side == Color::White { Wdl::Win } else { Wdl::Loss })
+    }
+}
+
+// Claims everything it is asked about is drawn
+struct AllDrawn;
+
+impl TablebaseProbe for AllDrawn {
+    fn max_pieces(&self) -> u32 { 5 }
+    fn probe_wdl(&self, _: &Board) -> Option<Wdl> { Some(Wdl::Draw) }
+}
+
+const KRK: &str = "4k3/8/8/8/8/8/8/R3K3 w - - 0 1";
+
+#[test]
+fn search_takes_the_tablebase_result() {
+    let mut board = Board::from_fen(KRK);
+    assert_eq!(piece_total(&board), 3);
+
+    let mut engine = SearchEngine::new();
+    let (_, plain) = engine.search(&mut board, 3, 60_000);
+    assert_eq!(engine.tb_hits, 0);
+
+    engine.tablebase = Some(Box::new(WhiteWins));
+    engine.tt.clear();
+    let (_, won) = engine.search(&mut board, 3, 60_000);
+    assert!(engine.tb_hits > 0);
+    assert!(won > plain + 10_000, "{} vs {}", won, plain);
+    // Probed one ply down, and not stored: the score is for that ply only
+    for mv in generate_moves(&board) {
+        board.make_move(mv);
+        assert!(engine.tt.probe(board.hash).is_none(), "{} stored", mv.to_uci());
+        board.unmake_move();
+    }
+
+    engine.tablebase = Some(Box::new(AllDrawn));
+    engine.tt.clear();
+    let (_, drawn) = engine.search(&mut board, 3, 60_000);
+    assert!(drawn.abs() <= engine.contempt.abs(), "{}", drawn);
+}
+
+#[test]
+fn larger_positions_are_not_probed() {
+    let mut engine = SearchEngine::new();
+    engine.tablebase = Some(Box::new(WhiteWins));
+    // Four pieces: past the mock's limit (one ply on, nothing is captured yet)
+    let mut board = Board::from_fen("4k3/8/8/8/8/8/8/R2QK3 w - - 0 1");
+    engine.search(&mut board, 1, 60_000);
+    assert_eq!(engine.tb_hits, 0);
+}
+
+#[test]
+fn syzygy_dir_scan_counts_wdl_tables() {
+    let dir = std::env::temp_dir().join(format!("abhin-syzygy-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    for name in ["KQvK.rtbw", "KRPvKR.rtbw", "KRPvKR.rtbz", "README"] {
+        std::fs::write(dir.join(name), b"").unwrap();
+    }
+    assert_eq!(scan_syzygy_dir(dir.to_str().unwrap()).unwrap(), (2, 5));
+    let _ = std::fs::remove_dir_all(&dir);
+    assert!(scan_syzygy_dir(dir.to_str().unwrap()).is_err());
+}
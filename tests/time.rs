@@ -0,0 +1,168 @@
+// time.rs — Time allocation responds to the MoveOverhead setting and the clocks
+
+use chess_engine::board::Board;
+use chess_engine::search::SearchEngine;
+use chess_engine::time::pick_time;
+use std::time::Instant;
+
+#[test]
+fn overhead_reduces_clock_allocation() {
+    let board = Board::start_pos();
+    let go = "go wtime 60000 btime 60000 winc 1000 binc 1000";
+    let (_, low)  = pick_time(go, &board, 0, 0);
+    let (_, high) = pick_time(go, &board, 500, 0);
+    assert_eq!(low - high, 500);
+}
+
+#[test]
+fn overhead_reduces_movetime() {
+    let board = Board::start_pos();
+    let (_, low)  = pick_time("go movetime 2000", &board, 30, 0);
+    let (_, high) = pick_time("go movetime 2000", &board, 300, 0);
+    assert_eq!((low, high), (1970, 1700));
+}
+
+#[test]
+fn overhead_never_zeroes_the_search() {
+    let board = Board::start_pos();
+    let (_, ms) = pick_time("go movetime 100", &board, 5000, 0);
+    assert!(ms > 0);
+}
+
+#[test]
+fn clock_lead_buys_more_time() {
+    let white = Board::start_pos();
+    let (_, even) = pick_time("go wtime 60000 btime 60000", &white, 0, 0);
+    let (_, ahead) = pick_time("go wtime 60000 btime 20000", &white, 0, 0);
+    let (_, behind) = pick_time("go wtime 60000 btime 120000", &white, 0, 0);
+    assert!(ahead > even, "{} vs {}", ahead, even);
+    assert_eq!(behind, even);
+    // Still within the 1/3-clock cap, and capped at half again
+    assert!(ahead <= 20000 && ahead <= even * 3 / 2);
+
+    // Black reads its own clock against White's
+    let black = Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+    let (_, black_ahead) = pick_time("go wtime 20000 btime 60000", &black, 0, 0);
+    assert_eq!(black_ahead, ahead);
+}
+
+#[test]
+fn depth_and_movetime_both_apply() {
+    let board = Board::start_pos();
+    assert_eq!(pick_time("go depth 30 movetime 500", &board, 30, 0), (12, 470));
+    assert_eq!(pick_time("go movetime 500 depth 3", &board, 30, 0), (3, 470));
+    // Depth plus a clock: the clock allocation still bounds the search
+    assert_eq!(pick_time("go depth 5 wtime 3000 btime 3000", &board, 0, 0), (5, 60));
+
+    let mut board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+    let (depth, ms) = pick_time("go depth 30 movetime 500", &board, 0, 0);
+    let mut engine = SearchEngine::new();
+    let start = Instant::now();
+    engine.search(&mut board, depth, ms);
+    assert!(start.elapsed().as_millis() < 1500, "{:?}", start.elapsed());
+}
+
+#[test]
+fn nodes_and_movetime_both_apply() {
+    let start = Board::start_pos();
+    // Nodes alone is no reason to fall back on a default clock
+    assert_eq!(pick_time("go nodes 5000", &start, 0, 0), (12, 300_000));
+    assert_eq!(pick_time("go nodes 1000000 movetime 50", &start, 0, 0), (12, 50));
+
+    // Slow: a million nodes take far longer than 50ms, so the clock stops it
+    let mut board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+    let mut engine = SearchEngine::new();
+    engine.node_limit = 1_000_000;
+    let clock = Instant::now();
+    engine.search(&mut board, 12, 50);
+    assert!(clock.elapsed().as_millis() < 1000, "{:?}", clock.elapsed());
+    assert!(engine.last_nodes() < 1_000_000);
+
+    // Fast: a small budget runs out long before the clock, exactly on the count
+    engine.node_limit = 3000;
+    engine.search(&mut board, 12, 60_000);
+    assert_eq!(engine.last_nodes(), 3000);
+}
+
+#[test]
+fn minimum_thinking_time_is_a_floor() {
+    let board = Board::start_pos();
+    // 3s on the clock: 60ms by default, the minimum lifts it
+    let go = "go wtime 3000 btime 3000";
+    assert_eq!(pick_time(go, &board, 0, 0).1, 60);
+    assert_eq!(pick_time(go, &board, 0, 800).1, 800);
+    // ... but never past a third of the clock
+    assert_eq!(pick_time(go, &board, 0, 5000).1, 1000);
+    // An explicit movetime is left alone
+    assert_eq!(pick_time("go movetime 100", &board, 0, 800).1, 100);
+}
+
+#[test]
+fn increment_only_control_is_conservative_early() {
+    // 5 minutes + 2 seconds, no movestogo
+    let go = "go wtime 300000 btime 300000 winc 2000 binc 2000";
+    let (_, opening) = pick_time(go, &Board::start_pos(), 0, 0);
+    // The base is spread over ~50 moves, the increment spent in full
+    assert_eq!(opening, 300_000 / 50 + 2000);
+    // An explicit movestogo still divides the clock by it
+    let (_, with_mtg) = pick_time(&format!("{} movestogo 25", go), &Board::start_pos(), 0, 0);
+    assert!(opening < with_mtg, "{} vs {}", opening, with_mtg);
+
+    // Fewer moves are left to budget for in an endgame
+    let endgame = Board::from_fen("8/5pk1/8/8/8/8/5PK1/8 w - - 0 40");
+    let (_, late) = pick_time(go, &endgame, 0, 0);
+    assert_eq!(late, 300_000 / 20 + 2000);
+}
+
+#[test]
+fn tiny_movetime_bounds_the_search() {
+    // The first clock check comes early, so even a few milliseconds are kept
+    // to within a small overshoot (root setup included, in a debug build)
+    for fen in [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    ] {
+        for ms in [1, 5, 20] {
+            let mut board = Board::from_fen(fen);
+            let mut engine = SearchEngine::new();
+            let clock = Instant::now();
+            let (mv, _) = engine.search(&mut board, 64, ms);
+            let elapsed = clock.elapsed().as_millis() as u64;
+            assert!(elapsed < ms + 250, "{}ms search took {}ms: {}", ms, elapsed, fen);
+            assert!(mv.from != mv.to, "no move in {}ms", ms);
+        }
+    }
+}
+
+#[test]
+fn best_move_stability_moves_the_soft_limit() {
+    let mut board = Board::start_pos();
+    let mut engine = SearchEngine::new();
+    engine.search(&mut board, 1, 1000);
+    let settled = engine.soft_limit(3, false, false);
+    let young = engine.soft_limit(1, false, false);
+    let changed = engine.soft_limit(0, true, false);
+    assert_eq!((settled, young, changed), (300, 500, 750));
+
+    // An iteration finishing at 400ms: a settled move stops there, a
+    // fresh one keeps going
+    assert!(400 >= settled);
+    assert!(400 < young && 400 < changed);
+}
+
+#[test]
+fn search_counts_best_move_changes() {
+    // A hanging queen: the capture is best from depth 1 on
+    let mut board = Board::from_fen("4k3/8/8/3q4/8/8/8/3QK3 w - - 0 1");
+    let mut engine = SearchEngine::new();
+    engine.deterministic = true;
+    let (mv, _) = engine.search(&mut board, 5, 60_000);
+    assert_eq!(mv.to_uci(), "d1d5");
+    assert_eq!(engine.best_move_changes, 0);
+
+    // The Italian: Nc3 at depth 4 gives way to d4 at depth 5
+    let mut board = Board::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3");
+    let (mv, _) = engine.search(&mut board, 5, 60_000);
+    assert_eq!(mv.to_uci(), "d2d4");
+    assert!(engine.best_move_changes >= 1);
+}
@@ -0,0 +1,93 @@
+// tt.rs — Transposition table replacement and occupancy
+
+use chess_engine::board::{Board, Move};
+use chess_engine::movegen::generate_moves;
+use chess_engine::search::TT;
+
+// Two keys that map to the same slot
+const OLD: u64 = 0x1234;
+const NEW: u64 = 0x1234 | (1 << 48);
+
+#[test]
+fn deep_entry_survives_within_a_search() {
+    let mut tt = TT::new();
+    tt.store(OLD, 20, 0, 0, Move::null());
+    for _ in 0..100 {
+        tt.store(NEW, 1, 0, 0, Move::null());
+    }
+    assert!(tt.probe(OLD).is_some());
+    assert!(tt.probe(NEW).is_none());
+}
+
+#[test]
+fn stale_deep_entry_is_replaced() {
+    let mut tt = TT::new();
+    tt.store(OLD, 20, 0, 0, Move::null());
+    tt.new_search();
+    for _ in 0..100 {
+        tt.store(NEW, 1, 0, 0, Move::null());
+    }
+    assert!(tt.probe(OLD).is_none());
+    assert!(tt.probe(NEW).is_some());
+}
+
+#[test]
+fn hashfull_counts_current_generation() {
+    let mut tt = TT::new();
+    assert_eq!(tt.hashfull(), 0);
+    for key in 0..500u64 {
+        tt.store(key, 1, 0, 0, Move::null());
+    }
+    assert_eq!(tt.hashfull(), 500);
+    tt.new_search();
+    assert_eq!(tt.hashfull(), 0);
+}
+
+#[test]
+fn exact_entry_survives_equal_depth_bounds() {
+    let mut tt = TT::new();
+    tt.store(OLD, 6, 42, 0, Move::null());
+    for flag in [1, 2, 1, 2] {
+        tt.store(NEW, 6, 0, flag, Move::null());
+    }
+    assert!(tt.probe(OLD).is_some());
+    assert!(tt.probe(NEW).is_none());
+
+    // A much deeper bound still wins the slot, as does an exact entry
+    tt.store(NEW, 8, 0, 1, Move::null());
+    assert!(tt.probe(OLD).is_none());
+    tt.store(OLD, 8, 0, 0, Move::null());
+    assert!(tt.probe(OLD).is_some());
+}
+
+#[test]
+fn tiny_table_replacement() {
+    // Two slots: even hashes share slot 0, odd ones slot 1
+    let mut tt = TT::with_entries(2);
+    tt.store(2, 5, 0, 1, Move::null());
+    tt.store(3, 1, 0, 1, Move::null());
+    assert!(tt.probe(2).is_some() && tt.probe(3).is_some());
+
+    // Shallower collider loses to the deeper entry; deeper one wins
+    tt.store(4, 4, 0, 1, Move::null());
+    assert!(tt.probe(2).is_some() && tt.probe(4).is_none());
+    tt.store(4, 6, 0, 1, Move::null());
+    assert!(tt.probe(2).is_none() && tt.probe(4).is_some());
+    assert!(tt.probe(3).is_some()); // the other slot is untouched
+
+    // Rounded up to a power of two
+    let mut three = TT::with_entries(3);
+    for key in 0..4u64 { three.store(key, 1, 0, 0, Move::null()); }
+    assert!((0..4u64).all(|key| three.probe(key).is_some()));
+}
+
+#[test]
+fn best_move_ignores_depth_but_not_moveless_entries() {
+    let board = Board::start_pos();
+    let mv = generate_moves(&board).into_iter().find(|m| m.to_uci() == "e2e4").unwrap();
+    let mut tt = TT::new();
+    tt.store(board.hash, 1, 0, 2, mv);
+    assert_eq!(tt.best_move(board.hash), Some(mv));
+    tt.store(board.hash, 5, 0, 0, Move::null());
+    assert_eq!(tt.best_move(board.hash), None);
+}
@@ -0,0 +1,566 @@
+// uci.rs — Drives the engine binary over stdin/stdout like a GUI would
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+struct Engine {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+}
+
+impl Engine {
+    fn start() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_chess-engine"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("engine binary should start");
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+
+        let (tx, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if tx.send(line).is_err() { break; }
+            }
+        });
+        Engine { child, stdin, lines }
+    }
+
+    fn send(&mut self, cmd: &str) {
+        writeln!(self.stdin, "{}", cmd).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    /// Next line starting with `prefix`, skipping others; None on timeout
+    fn expect(&self, prefix: &str, timeout: Duration) -> Option<String> {
+        let deadline = std::time::Instant::now() + timeout;
+        loop {
+            let left = deadline.checked_duration_since(std::time::Instant::now())?;
+            match self.lines.recv_timeout(left) {
+                Ok(line) if line.starts_with(prefix) => return Some(line),
+                Ok(_) => continue,
+                Err(_) => return None,
+            }
+        }
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[test]
+fn isready_answered_during_infinite_search() {
+    let mut e = Engine::start();
+    e.send("uci");
+    assert!(e.expect("uciok", Duration::from_secs(5)).is_some());
+
+    // Out of book, so the move has to come from the search
+    e.send("position fen r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+    e.send("go infinite");
+    thread::sleep(Duration::from_millis(300));
+
+    e.send("isready");
+    let mut seen = Vec::new();
+    let ready = loop {
+        match e.lines.recv_timeout(Duration::from_secs(2)) {
+            Ok(line) if line == "readyok" => break true,
+            Ok(line) => seen.push(line),
+            Err(_) => break false,
+        }
+    };
+    assert!(ready, "no readyok while searching");
+    assert!(!seen.iter().any(|l| l.starts_with("bestmove")),
+        "search ended before readyok: {:?}", seen);
+
+    e.send("stop");
+    assert!(e.expect("bestmove", Duration::from_secs(5)).is_some(),
+        "no bestmove after stop");
+    e.send("quit");
+}
+
+#[test]
+fn testsuite_prints_tally() {
+    // Same back-rank mate twice: once as the best move, once as the move to
+    // avoid, so exactly one of the two passes
+    let path = std::env::temp_dir().join(format!("abhin-suite-{}.epd", std::process::id()));
+    std::fs::write(&path, concat!(
+        "6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8#; id \"mate\";\n",
+        "6k1/5ppp/8/8/8/8/8/R5K1 w - - am Ra8#; id \"avoid\";\n",
+    )).unwrap();
+
+    let mut e = Engine::start();
+    e.send(&format!("testsuite {} 200", path.display()));
+    let first  = e.expect("mate:", Duration::from_secs(10));
+    let second = e.expect("avoid:", Duration::from_secs(10));
+    let tally  = e.expect("testsuite", Duration::from_secs(10));
+    e.send("quit");
+    let _ = std::fs::remove_file(&path);
+
+    assert_eq!(first.as_deref(), Some("mate: pass Ra8# (bm Ra8#)"));
+    assert_eq!(second.as_deref(), Some("avoid: fail Ra8# (am Ra8#)"));
+    assert_eq!(tally.as_deref(), Some("testsuite 1/2 passed"));
+}
+
+#[test]
+fn deterministic_searches_repeat_exactly() {
+    let mut e = Engine::start();
+    e.send("setoption name Deterministic value true");
+
+    let mut runs = Vec::new();
+    for _ in 0..2 {
+        e.send("position startpos");
+        e.send("go depth 5");
+        let mut out = Vec::new();
+        loop {
+            let line = e.lines.recv_timeout(Duration::from_secs(30)).expect("search output");
+            let done = line.starts_with("bestmove");
+            out.push(line);
+            if done { break; }
+        }
+        runs.push(out);
+    }
+    e.send("quit");
+
+    assert!(runs[0].iter().any(|l| l.starts_with("info depth 5")));
+    assert_eq!(runs[0], runs[1]);
+}
+
+#[test]
+fn ponderhit_keeps_the_ponder_search() {
+    let mut e = Engine::start();
+    e.send("setoption name OwnBook value false");
+    let position = "position fen r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+    // Pondering runs well past the movetime without answering: the clock
+    // only starts at ponderhit
+    e.send(position);
+    e.send("go ponder movetime 200");
+    let mut pondered = 0;
+    let deadline = Instant::now() + Duration::from_millis(1000);
+    while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+        let Ok(line) = e.lines.recv_timeout(left) else { break };
+        assert!(!line.starts_with("bestmove"), "answered while pondering: {}", line);
+        pondered = pondered.max(info_depth(&line));
+    }
+    assert!(pondered > 0, "no search output while pondering");
+
+    // Same search, now on the clock: no restart from depth 1, and the
+    // answer comes within the movetime (plus slack), not after a fresh search
+    e.send("ponderhit");
+    let hit = Instant::now();
+    loop {
+        let line = e.lines.recv_timeout(Duration::from_secs(5)).expect("bestmove after ponderhit");
+        if line.starts_with("bestmove") { break; }
+        let depth = info_depth(&line);
+        assert!(depth == 0 || depth >= pondered, "restarted at depth {} after {}", depth, pondered);
+    }
+    assert!(hit.elapsed() < Duration::from_millis(1500), "{:?} after ponderhit", hit.elapsed());
+
+    // A ponder miss: stop ends the ponder search at once with its move
+    e.send(position);
+    e.send("go ponder movetime 200");
+    thread::sleep(Duration::from_millis(300));
+    e.send("stop");
+    assert!(e.expect("bestmove", Duration::from_millis(1000)).is_some_and(|l| l != "bestmove 0000"));
+    e.send("quit");
+}
+
+// Depth of an "info depth N ... score" line, 0 for any other line
+fn info_depth(line: &str) -> u32 {
+    let mut t = line.split_whitespace();
+    if !line.contains(" score ") || t.next() != Some("info") || t.next() != Some("depth") { return 0; }
+    t.next().and_then(|d| d.parse().ok()).unwrap_or(0)
+}
+
+#[test]
+fn finished_ponder_search_waits_for_ponderhit() {
+    let mut e = Engine::start();
+    // Mate in one: the search ends at once, but must hold its bestmove
+    e.send("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+    e.send("go ponder movetime 100");
+    assert!(e.expect("bestmove", Duration::from_millis(500)).is_none());
+    e.send("ponderhit");
+    assert_eq!(e.expect("bestmove", Duration::from_secs(5)).as_deref(), Some("bestmove a1a8"));
+    e.send("quit");
+}
+
+#[test]
+fn terminal_root_answers_null_move() {
+    let mut e = Engine::start();
+    for (fen, info) in [
+        // Fool's mate: White is checkmated
+        ("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3", "info string checkmate"),
+        ("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", "info string stalemate"),
+    ] {
+        e.send(&format!("position fen {}", fen));
+        e.send("go movetime 200");
+        assert!(e.expect(info, Duration::from_secs(5)).is_some(), "no {:?} for {}", info, fen);
+        assert_eq!(e.expect("bestmove", Duration::from_secs(5)).as_deref(), Some("bestmove 0000"));
+    }
+    e.send("quit");
+}
+
+#[test]
+fn new_game_commands_end_a_running_search() {
+    let mut e = Engine::start();
+    e.send("setoption name OwnBook value false");
+    for new_game in ["ucinewgame", "position fen 8/8/4k3/8/8/4K3/8/8 w - - 0 1"] {
+        e.send("position startpos moves e2e4");
+        e.send("go infinite");
+        thread::sleep(Duration::from_millis(200));
+        // No stop: the command itself ends the search rather than waiting on it
+        e.send(new_game);
+        assert!(e.expect("bestmove", Duration::from_secs(5)).is_some(), "{} hung", new_game);
+        e.send("go depth 1");
+        assert!(e.expect("bestmove", Duration::from_secs(30)).is_some(), "{}", new_game);
+    }
+}
+
+#[test]
+fn learning_file_records_the_game() {
+    let path = std::env::temp_dir().join(format!("abhin-uci-learn-{}.txt", std::process::id()));
+    let path = path.to_str().unwrap().to_string();
+    let _ = std::fs::remove_file(&path);
+
+    let mut e = Engine::start();
+    e.send(&format!("setoption name LearningFile value {}", path));
+    e.send("setoption name OwnBook value false");
+    let mate_in_one = |e: &mut Engine| {
+        e.send("position startpos moves f2f3 e7e5 g2g4");
+        e.send("go depth 2");
+        let best = e.expect("bestmove", Duration::from_secs(30)).expect("bestmove");
+        assert_eq!(best, "bestmove d8h4");
+    };
+    mate_in_one(&mut e);
+
+    // A takeback is no game result, decisive score or not
+    e.send("position startpos moves f2f3 e7e5");
+    e.send("isready");
+    assert!(e.expect("readyok", Duration::from_secs(10)).is_some());
+    assert!(std::fs::metadata(&path).is_err(), "takeback recorded");
+
+    // The GUI reports the mate: a win for Black, recorded straight away
+    mate_in_one(&mut e);
+    e.send("position startpos moves f2f3 e7e5 g2g4 d8h4");
+    e.send("isready");
+    assert!(e.expect("readyok", Duration::from_secs(10)).is_some());
+    let text = std::fs::read_to_string(&path).expect("learning file written");
+    // Black's two moves, both credited with one win
+    assert_eq!(text.lines().count(), 2);
+    assert!(text.lines().all(|l| l.ends_with(" 1 0 0")), "{}", text);
+
+    // ... and only once, whatever ucinewgame follows
+    e.send("ucinewgame");
+    e.send("isready");
+    assert!(e.expect("readyok", Duration::from_secs(10)).is_some());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), text);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn malformed_position_commands_fall_back_to_the_start() {
+    let mut e = Engine::start();
+    e.send("setoption name OwnBook value false");
+    // Each falls back to (or starts from) the start position and says so;
+    // the engine then still answers "go" with a legal move
+    for (cmd, black_to_move) in [
+        ("position", false),
+        ("position moves e2e4", true),
+        ("position fen", false),
+        ("position fen moves e2e4", false),
+        ("position fen 8/8/8/8/8/8/8/8 w - - 0 1", false),
+        ("position startpos moves e2e4 e2e4", true),
+    ] {
+        e.send(cmd);
+        assert!(e.expect("info string", Duration::from_secs(10)).is_some(), "{}", cmd);
+        e.send("go depth 1");
+        let best = e.expect("bestmove", Duration::from_secs(30)).expect("bestmove");
+        let from_rank = best.as_bytes()[10];
+        assert_eq!(matches!(from_rank, b'7' | b'8'), black_to_move, "{}: {}", cmd, best);
+    }
+}
+
+#[test]
+fn debug_on_adds_info_strings_to_a_search() {
+    let mut e = Engine::start();
+    e.send("register later");
+    assert_eq!(e.expect("registration", Duration::from_secs(5)).as_deref(), Some("registration ok"));
+
+    // Lines of a depth-2 search, up to its bestmove
+    let search = |e: &mut Engine| {
+        e.send("position fen r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        e.send("go depth 2");
+        let mut seen = Vec::new();
+        while let Ok(line) = e.lines.recv_timeout(Duration::from_secs(30)) {
+            if line.starts_with("bestmove") { return seen; }
+            seen.push(line);
+        }
+        panic!("no bestmove");
+    };
+    let debug_lines = |lines: &[String]| lines.iter().filter(|l| l.starts_with("info string debug")).count();
+
+    e.send("debug on");
+    let on = search(&mut e);
+    assert!(on.iter().any(|l| l.starts_with("info string debug hash ") && l.contains(" legal 48 ")), "{:?}", on);
+    e.send("debug off");
+    assert_eq!(debug_lines(&search(&mut e)), 0);
+    e.send("quit");
+}
+
+#[test]
+fn debug_during_infinite_search_does_not_block_stop() {
+    let mut e = Engine::start();
+    e.send("setoption name OwnBook value false");
+    e.send("position startpos");
+    e.send("go infinite");
+    assert!(e.expect("info depth", Duration::from_secs(10)).is_some());
+    e.send("debug on");
+    e.send("debug off");
+    e.send("stop");
+    assert!(e.expect("bestmove", Duration::from_secs(2)).is_some(), "stop after debug was not read");
+    e.send("quit");
+}
+
+#[test]
+fn options_during_infinite_search_does_not_block_stop() {
+    let mut e = Engine::start();
+    e.send("setoption name OwnBook value false");
+    e.send("position startpos");
+    e.send("go infinite");
+    assert!(e.expect("info depth", Duration::from_secs(10)).is_some());
+    e.send("options");
+    assert!(e.expect("info string options unavailable", Duration::from_secs(2)).is_some());
+    e.send("stop");
+    assert!(e.expect("bestmove", Duration::from_secs(2)).is_some());
+    // Once the search is over the summary is back
+    e.send("options");
+    assert!(e.expect("info string options Hash 64", Duration::from_secs(2)).is_some());
+    e.send("quit");
+}
+
+#[test]
+fn clear_hash_empties_the_table() {
+    let mut e = Engine::start();
+    e.send("setoption name OwnBook value false");
+    // Nodes of a depth-4 search of Kiwipete, from its last score line
+    let nodes = |e: &mut Engine| {
+        e.send("position fen r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        e.send("go depth 4");
+        let mut nodes = 0;
+        while let Ok(line) = e.lines.recv_timeout(Duration::from_secs(30)) {
+            if line.starts_with("bestmove") { return nodes; }
+            if line.contains(" score ") {
+                let mut t = line.split_whitespace().skip_while(|&w| w != "nodes").skip(1);
+                nodes = t.next().and_then(|n| n.parse().ok()).unwrap_or(0);
+            }
+        }
+        panic!("no bestmove");
+    };
+    let fresh: u64 = nodes(&mut e);
+    let warm = nodes(&mut e);
+    e.send("setoption name Clear Hash");
+    let cleared = nodes(&mut e);
+    // A warm table cuts the repeat short; a cleared one searches like new
+    assert!(warm < fresh / 2, "warm {warm} vs fresh {fresh}");
+    assert_eq!(cleared, fresh);
+    e.send("quit");
+}
+
+#[test]
+fn unrelated_position_starts_a_new_game() {
+    let mut e = Engine::start();
+    e.send("debug on");
+    // Lines up to the next readyok
+    let until_ready = |e: &mut Engine| {
+        e.send("isready");
+        let mut seen = Vec::new();
+        while let Ok(line) = e.lines.recv_timeout(Duration::from_secs(10)) {
+            if line == "readyok" { return seen; }
+            seen.push(line);
+        }
+        panic!("no readyok");
+    };
+    let new_game = |lines: &[String]| lines.iter().any(|l| l.contains("new game"));
+
+    e.send("position startpos moves e2e4");
+    e.send("position startpos moves e2e4 e7e5");
+    assert!(!new_game(&until_ready(&mut e)));
+    e.send("position fen 8/8/4k3/8/8/4K3/8/8 w - - 0 1");
+    assert!(new_game(&until_ready(&mut e)));
+}
+
+#[test]
+fn first_isready_reports_options() {
+    let mut e = Engine::start();
+    e.send("setoption name Hash value 128");
+    e.send("setoption name Contempt value 25");
+    e.send("isready");
+    let info = e.expect("info string options", Duration::from_secs(10)).expect("options summary");
+    assert!(info.contains("Hash 128,") && info.contains("Contempt 25,"), "{}", info);
+    assert!(e.expect("readyok", Duration::from_secs(10)).is_some());
+
+    // Only once unasked; "options" repeats it on demand
+    e.send("isready");
+    assert_eq!(e.expect("", Duration::from_secs(10)).as_deref(), Some("readyok"));
+    e.send("options");
+    assert!(e.expect("info string options Hash 128,", Duration::from_secs(10)).is_some());
+}
+
+#[test]
+fn quit_during_ponder_search_exits_cleanly() {
+    let mut e = Engine::start();
+    e.send("position startpos moves e2e4");
+    e.send("go ponder infinite");
+    thread::sleep(Duration::from_millis(200));
+    e.send("quit");
+
+    // The running search still gets its bestmove out in full
+    let best = e.expect("bestmove", Duration::from_secs(5)).expect("no bestmove before exit");
+    assert!(best.split_whitespace().nth(1).is_some_and(|m| m.len() >= 4), "{}", best);
+    let deadline = std::time::Instant::now() + Duration::from_secs(5);
+    let status = loop {
+        if let Some(status) = e.child.try_wait().unwrap() { break status; }
+        assert!(std::time::Instant::now() < deadline, "engine still running after quit");
+        thread::sleep(Duration::from_millis(20));
+    };
+    assert!(status.success());
+}
+
+#[test]
+fn info_interval_throttles_depth_lines() {
+    // Depth lines (with a score) up to and including bestmove
+    fn depth_lines(e: &Engine) -> Vec<String> {
+        let mut out = Vec::new();
+        loop {
+            let line = e.lines.recv_timeout(Duration::from_secs(30)).expect("search output");
+            if line.starts_with("bestmove") { return out; }
+            if line.starts_with("info depth") && line.contains(" score ") { out.push(line); }
+        }
+    }
+
+    let mut e = Engine::start();
+    e.send("position fen r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+    e.send("go depth 5");
+    let full = depth_lines(&e);
+
+    e.send("setoption name InfoInterval value 100000");
+    e.send("go depth 5");
+    let throttled = depth_lines(&e);
+    e.send("quit");
+
+    assert_eq!(full.len(), 5);
+    assert!(throttled.len() < full.len(), "{:?}", throttled);
+    // The first depth goes out at once, the final one before bestmove
+    assert!(throttled.first().unwrap().starts_with("info depth 1 "));
+    assert!(throttled.last().unwrap().starts_with("info depth 5 "));
+}
+
+#[test]
+fn single_searchmove_is_always_played() {
+    let mut e = Engine::start();
+    e.send("setoption name OwnBook value false");
+    // Weak moves the search would never choose on its own
+    for (pos, mv) in [
+        ("startpos", "g1h3"),
+        ("startpos moves e2e4 e7e5", "e1e2"),
+        ("fen r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", "a1b1"),
+    ] {
+        e.send(&format!("position {}", pos));
+        for go in ["go depth 4", "go movetime 200"] {
+            e.send(&format!("{} searchmoves {}", go, mv));
+            let best = e.expect("bestmove", Duration::from_secs(30)).expect("bestmove");
+            assert_eq!(best.split_whitespace().nth(1), Some(mv), "{} / {}", pos, go);
+        }
+    }
+    e.send("quit");
+}
+
+#[test]
+fn long_searches_report_currmove() {
+    let mut e = Engine::start();
+    e.send("setoption name OwnBook value false");
+    e.send("position fen r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+    let start = Instant::now();
+    e.send("go movetime 4500");
+    let mut first = None;
+    let mut lines = Vec::new();
+    while let Ok(line) = e.lines.recv_timeout(Duration::from_secs(30)) {
+        if line.starts_with("bestmove") { break; }
+        if line.contains(" currmove ") {
+            first.get_or_insert(start.elapsed());
+            lines.push(line);
+        }
+    }
+    e.send("quit");
+
+    // Only past the first three seconds, then for each root move
+    let first = first.expect("no currmove line");
+    assert!(first >= Duration::from_secs(3), "{:?}", first);
+    for line in &lines {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        assert!(matches!(words[..], ["info", "depth", _, "currmove", mv, "currmovenumber", n]
+            if mv.len() >= 4 && n.parse::<u32>().is_ok_and(|n| n >= 1)), "{}", line);
+    }
+}
+
+#[test]
+fn multipv_lists_only_searchmoves() {
+    // multipv lines of the deepest depth, up to bestmove
+    fn last_depth_lines(e: &Engine) -> Vec<String> {
+        let (mut depth, mut lines) = (String::new(), Vec::new());
+        loop {
+            let line = e.lines.recv_timeout(Duration::from_secs(30)).expect("search output");
+            if line.starts_with("bestmove") { return lines; }
+            if !line.contains(" multipv ") { continue; }
+            let d = line.split_whitespace().nth(2).unwrap().to_string();
+            if d != depth { depth = d; lines.clear(); }
+            lines.push(line);
+        }
+    }
+    let pv_move = |line: &String| line.split_whitespace().last().unwrap().to_string();
+
+    let mut e = Engine::start();
+    e.send("setoption name OwnBook value false");
+    e.send("setoption name MultiPV value 3");
+    e.send("position startpos");
+    e.send("go depth 4");
+    let all = last_depth_lines(&e);
+    assert_eq!(all.len(), 3, "{:?}", all);
+
+    e.send("go depth 4 searchmoves e2e4 d2d4");
+    let restricted = last_depth_lines(&e);
+    e.send("quit");
+
+    assert_eq!(restricted.len(), 2, "{:?}", restricted);
+    let mut moves: Vec<String> = restricted.iter().map(pv_move).collect();
+    moves.sort();
+    assert_eq!(moves, ["d2d4", "e2e4"]);
+    assert!(restricted[0].contains(" multipv 1 ") && restricted[1].contains(" multipv 2 "));
+}
+
+#[cfg(feature = "syzygy")]
+#[test]
+fn syzygy_path_option_reports_the_tables() {
+    let dir = std::env::temp_dir().join(format!("abhin-uci-syzygy-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("KBNvK.rtbw"), b"").unwrap();
+
+    let mut e = Engine::start();
+    e.send("uci");
+    assert!(e.expect("option name SyzygyPath type string", Duration::from_secs(5)).is_some());
+    e.send(&format!("setoption name SyzygyPath value {}", dir.display()));
+    let info = e.expect("info string SyzygyPath", Duration::from_secs(5));
+    let _ = std::fs::remove_dir_all(&dir);
+    // Found, but nothing in the binary can probe them
+    assert!(info.is_some_and(|l| l.ends_with(
+        ": 1 WDL tables, up to 4 pieces; no tablebase reader is installed, so they are not used")));
+}